@@ -1,42 +1,250 @@
+use std::rc::Rc;
+
 use crate::scanner::{Object, Token};
 
+#[derive(Clone)]
+pub struct Assign {
+    pub name: Token,
+    pub value: Box<Expr>,
+}
+
+#[derive(Clone)]
 pub struct Binary {
     pub left: Box<Expr>,
     pub operator: Token,
     pub right: Box<Expr>,
 }
 
+#[derive(Clone)]
+pub struct Call {
+    pub callee: Box<Expr>,
+    pub paren: Token,
+    pub arguments: Vec<Expr>,
+    /// `name: value` arguments, which always follow the positional ones.
+    pub named_arguments: Vec<(Token, Expr)>,
+}
+
+#[derive(Clone)]
+pub struct Conditional {
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Expr>,
+    pub else_branch: Box<Expr>,
+}
+
+/// A function: a `fun` declaration or expression, or an arrow lambda, whose
+/// expression body is wrapped in a `return`.
+#[derive(Clone)]
+pub struct Function {
+    pub name: Option<Token>,
+    pub params: Vec<Param>,
+    pub body: Rc<Vec<Stmt>>,
+}
+
+/// A function parameter. Only the last one can be a `...rest` parameter, and
+/// it has no default.
+#[derive(Clone)]
+pub struct Param {
+    pub name: Token,
+    pub default: Option<Expr>,
+    pub rest: bool,
+}
+
+#[derive(Clone)]
+pub struct Get {
+    pub object: Box<Expr>,
+    pub name: Token,
+}
+
+#[derive(Clone)]
 pub struct Grouping {
     pub expression: Box<Expr>,
 }
 
+/// A string with embedded expressions. The parts alternate between the
+/// literal segments and the interpolated expressions.
+#[derive(Clone)]
+pub struct Interpolation {
+    pub parts: Vec<Expr>,
+}
+
+#[derive(Clone)]
+pub struct Index {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+}
+
+#[derive(Clone)]
+pub struct List {
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Clone)]
 pub struct Literal {
     pub value: Object,
 }
 
+/// A map literal. `brace` is kept to report keys that can't be hashed.
+#[derive(Clone)]
+pub struct Map {
+    pub brace: Token,
+    pub entries: Vec<(Expr, Expr)>,
+}
+
+/// A set literal such as `{1, 2}`. `{}` is an empty map, so the empty set is
+/// written `{,}`.
+#[derive(Clone)]
+pub struct Set {
+    pub brace: Token,
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Clone)]
+pub struct SetIndex {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub index: Box<Expr>,
+    pub value: Box<Expr>,
+}
+
+#[derive(Clone)]
+pub struct Tuple {
+    pub elements: Vec<Expr>,
+}
+
+#[derive(Clone)]
+pub struct Variable {
+    pub name: Token,
+}
+
+/// `object[start:end]`, where either bound may be left out.
+#[derive(Clone)]
+pub struct Slice {
+    pub object: Box<Expr>,
+    pub bracket: Token,
+    pub start: Option<Box<Expr>>,
+    pub end: Option<Box<Expr>>,
+}
+
+#[derive(Clone)]
 pub struct Unary {
     pub operator: Token,
     pub right: Box<Expr>,
 }
 
+#[derive(Clone)]
 pub enum Expr {
+    Assign(Assign),
     Binary(Binary),
+    Call(Call),
+    Conditional(Conditional),
+    Function(Function),
+    Get(Get),
     Grouping(Grouping),
+    Index(Index),
+    Interpolation(Interpolation),
+    List(List),
     Literal(Literal),
+    Map(Map),
+    Set(Set),
+    SetIndex(SetIndex),
+    Slice(Slice),
+    Tuple(Tuple),
     Unary(Unary),
+    Variable(Variable),
 }
 
+#[derive(Clone)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+}
+
+/// The `catch (name) { body }` clause of a `try` statement.
+#[derive(Clone)]
+pub struct Catch {
+    pub name: Token,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Clone)]
 pub struct Expression {
     pub expression: Expr,
 }
 
+/// `for (var name in iterable) body`. `keyword` is the `in` token, where
+/// errors about the iterable are reported.
+#[derive(Clone)]
+pub struct ForIn {
+    pub name: Token,
+    pub keyword: Token,
+    pub iterable: Expr,
+    pub body: Box<Stmt>,
+}
+
+#[derive(Clone)]
+pub struct If {
+    pub condition: Expr,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+}
+
+#[derive(Clone)]
 pub struct Print {
     pub expression: Expr,
 }
 
+#[derive(Clone)]
+pub struct Return {
+    pub keyword: Token,
+    pub value: Option<Expr>,
+}
+
+#[derive(Clone)]
+pub struct Throw {
+    pub keyword: Token,
+    pub value: Expr,
+}
+
+/// `try { body } catch (name) { ... } finally { ... }`, where at least one of
+/// the clauses is present.
+#[derive(Clone)]
+pub struct Try {
+    pub body: Vec<Stmt>,
+    pub catch: Option<Catch>,
+    pub finally: Option<Vec<Stmt>>,
+}
+
+#[derive(Clone)]
+pub struct Var {
+    pub name: Token,
+    pub initializer: Option<Expr>,
+    /// Whether this is a `const` declaration, which can't be assigned to.
+    pub constant: bool,
+    /// The `///` doc comments just before the declaration, for documentation
+    /// tooling.
+    pub docs: Vec<Token>,
+}
+
+#[derive(Clone)]
+pub struct While {
+    pub condition: Expr,
+    pub body: Box<Stmt>,
+}
+
+#[derive(Clone)]
 pub enum Stmt {
+    Block(Block),
+    Break,
+    Continue,
     Expression(Expression),
+    ForIn(ForIn),
+    If(If),
     Print(Print),
+    Return(Return),
+    Throw(Throw),
+    Try(Try),
+    Var(Var),
+    While(While),
 }
 
 // pub trait Visitor {
@@ -0,0 +1,238 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    interner::{LoxString, Symbol},
+    interpreter::{number_error, undefined_key_error, RuntimeError, StackFrame},
+    map::MapKey,
+    number::{self, NumberError},
+    scanner::{Object, Token},
+};
+
+/// A method of a built-in type, bound to the value it was looked up on.
+#[derive(Debug, PartialEq)]
+pub struct BuiltinMethod {
+    pub receiver: Object,
+    pub name: Symbol,
+    pub arity: usize,
+}
+
+/// A function that is built into the language, like `int`.
+#[derive(Debug, PartialEq)]
+pub struct BuiltinFunction {
+    pub name: Symbol,
+    pub arity: usize,
+}
+
+/// The functions defined in the global scope of every program.
+pub fn functions() -> Vec<BuiltinFunction> {
+    ["int", "decimal", "float"]
+        .into_iter()
+        .map(|name| BuiltinFunction {
+            name: Symbol::intern(name),
+            arity: 1,
+        })
+        .collect()
+}
+
+/// Calls a function from `functions`. The caller has already checked that
+/// the number of arguments matches its arity.
+pub fn call_function(
+    function: &BuiltinFunction,
+    arguments: Vec<Object>,
+    paren: &Token,
+) -> Result<Object, RuntimeError> {
+    let argument = arguments.into_iter().next().unwrap();
+    let convert = match function.name.as_str() {
+        "int" => number::to_int,
+        "decimal" => number::to_decimal,
+        "float" => number::to_float,
+        name => unreachable!("no built-in function {name}"),
+    };
+    convert(argument).map_err(|error| match error {
+        NumberError::NotNumber => {
+            RuntimeError::new(paren.clone(), "Argument must be a number.".to_owned())
+        }
+        error => number_error(paren.clone(), error),
+    })
+}
+
+/// The integers from `start` up to `end`, which is included only if
+/// `inclusive` is set. Ranges are never materialized; iterating one counts
+/// through it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+}
+
+impl Range {
+    pub fn len(&self) -> i128 {
+        let end = self.end as i128 + self.inclusive as i128;
+        (end - self.start as i128).max(0)
+    }
+}
+
+/// The value a `catch` clause receives for a runtime error.
+#[derive(Debug, PartialEq)]
+pub struct ErrorObject {
+    pub message: String,
+    pub line: i32,
+    /// Where the error happened, innermost first.
+    pub stack: Vec<StackFrame>,
+}
+
+/// Looks up a field of a built-in type, like the `message` of an error.
+pub fn get_property(object: &Object, name: Symbol) -> Option<Object> {
+    match (object, name.as_str()) {
+        (Object::Error(error), "message") => {
+            Some(Object::Str(LoxString::from(error.message.as_str())))
+        }
+        (Object::Error(error), "line") => Some(Object::Int(error.line as i64)),
+        (Object::Error(error), "stack") => {
+            let frames = error
+                .stack
+                .iter()
+                .map(|frame| Object::Str(LoxString::from(frame.to_string())))
+                .collect();
+            Some(Object::List(Rc::new(RefCell::new(frames))))
+        }
+        _ => None,
+    }
+}
+
+/// Looks up `name` on `receiver`, returning `None` if its type has no such
+/// method.
+pub fn get_method(receiver: Object, name: Symbol) -> Option<BuiltinMethod> {
+    let arity = match (&receiver, name.as_str()) {
+        (Object::List(_), "len" | "pop") => 0,
+        (Object::List(_), "push" | "remove") => 1,
+        (Object::List(_), "insert") => 2,
+        (Object::Map(_), "len" | "keys" | "values") => 0,
+        (Object::Map(_), "has" | "remove") => 1,
+        (Object::Set(_), "len") => 0,
+        (Object::Set(_), "add" | "has" | "remove") => 1,
+        (Object::Tuple(_) | Object::Range(_), "len") => 0,
+        _ => return None,
+    };
+    Some(BuiltinMethod {
+        receiver,
+        name,
+        arity,
+    })
+}
+
+/// Calls a method returned by `get_method`. The caller has already checked
+/// that the number of arguments matches its arity.
+pub fn call_method(
+    method: &BuiltinMethod,
+    arguments: Vec<Object>,
+    paren: &Token,
+) -> Result<Object, RuntimeError> {
+    let mut arguments = arguments.into_iter();
+    let mut argument = || arguments.next().unwrap();
+
+    match (&method.receiver, method.name.as_str()) {
+        (Object::List(list), "len") => Ok(Object::Int(list.borrow().len() as i64)),
+        (Object::List(list), "push") => {
+            list.borrow_mut().push(argument());
+            Ok(Object::Nil)
+        }
+        (Object::List(list), "pop") => list.borrow_mut().pop().ok_or_else(|| {
+            RuntimeError::new(paren.clone(), "Can't pop from an empty list.".to_owned())
+        }),
+        (Object::List(list), "insert") => {
+            let index = argument();
+            let mut list = list.borrow_mut();
+            // inserting at the length appends
+            let index = list_index(&index, list.len() + 1, paren)?;
+            list.insert(index, argument());
+            Ok(Object::Nil)
+        }
+        (Object::List(list), "remove") => {
+            let index = argument();
+            let mut list = list.borrow_mut();
+            let index = list_index(&index, list.len(), paren)?;
+            Ok(list.remove(index))
+        }
+        (Object::Map(map), "len") => Ok(Object::Int(map.borrow().len() as i64)),
+        (Object::Map(map), "has") => {
+            let key = MapKey::new(argument(), paren)?;
+            Ok(Object::Bool(map.borrow().contains_key(&key)))
+        }
+        (Object::Map(map), "keys") => {
+            let keys = map.borrow().keys().map(|key| key.0.clone()).collect();
+            Ok(Object::List(Rc::new(RefCell::new(keys))))
+        }
+        (Object::Map(map), "values") => {
+            let values = map.borrow().values().cloned().collect();
+            Ok(Object::List(Rc::new(RefCell::new(values))))
+        }
+        (Object::Map(map), "remove") => {
+            let key = MapKey::new(argument(), paren)?;
+            // shifting keeps the remaining entries in insertion order
+            let value = map.borrow_mut().shift_remove(&key);
+            value.ok_or_else(|| undefined_key_error(paren.clone(), key))
+        }
+        (Object::Set(set), "len") => Ok(Object::Int(set.borrow().len() as i64)),
+        (Object::Set(set), "add") => {
+            set.borrow_mut().insert(MapKey::new(argument(), paren)?);
+            Ok(Object::Nil)
+        }
+        (Object::Set(set), "has") => {
+            let element = MapKey::new(argument(), paren)?;
+            Ok(Object::Bool(set.borrow().contains(&element)))
+        }
+        (Object::Set(set), "remove") => {
+            let element = MapKey::new(argument(), paren)?;
+            Ok(Object::Bool(set.borrow_mut().shift_remove(&element)))
+        }
+        (Object::Tuple(tuple), "len") => Ok(Object::Int(tuple.len() as i64)),
+        (Object::Range(range), "len") => Ok(number::from_bigint(range.len().into())),
+        _ => unreachable!("no built-in method {}", method.name),
+    }
+}
+
+/// Resolves a possibly negative index into a list of length `len`, where
+/// `-1` is the last element.
+pub fn list_index(index: &Object, len: usize, token: &Token) -> Result<usize, RuntimeError> {
+    let index = match index {
+        Object::Int(index) => *index,
+        _ => {
+            return Err(RuntimeError::new(
+                token.clone(),
+                "List index must be an integer.".to_owned(),
+            ))
+        }
+    };
+    let resolved = if index < 0 { index + len as i64 } else { index };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(RuntimeError::new(
+            token.clone(),
+            "Index out of bounds.".to_owned(),
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+/// Resolves an optional slice bound into a list of length `len`. Like
+/// Python, out of range bounds are clamped rather than reported.
+pub fn slice_bound(
+    bound: Option<Object>,
+    default: usize,
+    len: usize,
+    token: &Token,
+) -> Result<usize, RuntimeError> {
+    let bound = match bound {
+        None => return Ok(default),
+        Some(Object::Int(bound)) => bound,
+        Some(_) => {
+            return Err(RuntimeError::new(
+                token.clone(),
+                "Slice bounds must be integers.".to_owned(),
+            ))
+        }
+    };
+    let resolved = if bound < 0 { bound + len as i64 } else { bound };
+    Ok(resolved.clamp(0, len as i64) as usize)
+}
@@ -0,0 +1,94 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    interner::Symbol,
+    interpreter::RuntimeError,
+    scanner::{Object, Token},
+};
+
+/// The variables of one scope, chained to the scope it is nested in.
+pub struct Environment {
+    values: HashMap<Symbol, Object>,
+    /// The names in `values` that were declared with `const`.
+    constants: HashSet<Symbol>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Option<Rc<RefCell<Environment>>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing,
+        }
+    }
+
+    /// Defines `name` in this scope, shadowing or replacing any earlier one
+    /// unless that is a constant. The parser rejects most redeclarations of
+    /// constants, but it can't see those declared in earlier REPL lines.
+    pub fn define(&mut self, name: &Token, value: Object) -> Result<(), RuntimeError> {
+        let symbol = name.lexeme.symbol();
+        if self.constants.contains(&symbol) {
+            return Err(RuntimeError::new(
+                name.clone(),
+                format!("Can't redeclare constant '{}'.", name.lexeme),
+            ));
+        }
+        self.values.insert(symbol, value);
+        Ok(())
+    }
+
+    /// Defines `name` in this scope like `define`, but as a constant.
+    pub fn define_constant(&mut self, name: &Token, value: Object) -> Result<(), RuntimeError> {
+        self.define(name, value)?;
+        self.constants.insert(name.lexeme.symbol());
+        Ok(())
+    }
+
+    /// Defines one of the globals built into the language, which programs
+    /// are free to redefine.
+    pub fn define_builtin(&mut self, name: Symbol, value: Object) {
+        self.values.insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, RuntimeError> {
+        match self.values.get(&name.lexeme.symbol()) {
+            Some(value) => Ok(value.clone()),
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow().get(name),
+                None => Err(undefined_variable_error(name)),
+            },
+        }
+    }
+
+    /// Assigns to the innermost `name` in scope. The parser rejects most
+    /// assignments to constants, but it can't see constants declared in
+    /// earlier REPL lines or after the function doing the assigning.
+    pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), RuntimeError> {
+        match self.values.get_mut(&name.lexeme.symbol()) {
+            Some(_) if self.constants.contains(&name.lexeme.symbol()) => Err(RuntimeError::new(
+                name.clone(),
+                format!("Cannot assign to constant '{}'.", name.lexeme),
+            )),
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+                None => Err(undefined_variable_error(name)),
+            },
+        }
+    }
+}
+
+fn undefined_variable_error(name: &Token) -> RuntimeError {
+    RuntimeError::new(
+        name.clone(),
+        format!("Undefined variable '{}'.", name.lexeme),
+    )
+}
@@ -0,0 +1,127 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    ast::{Param, Stmt},
+    environment::Environment,
+    interner::Symbol,
+    interpreter::RuntimeError,
+    scanner::{Object, Token},
+};
+
+/// A function value: the code of a named function, `fun` expression or
+/// arrow lambda, together with the scope it was created in.
+pub struct Closure {
+    /// `None` for anonymous functions and lambdas.
+    pub name: Option<Symbol>,
+    pub params: Vec<Param>,
+    pub body: Rc<Vec<Stmt>>,
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+impl Closure {
+    /// Matches the arguments of a call to the parameters, returning a value
+    /// for each parameter, or `None` where its default should be used. A
+    /// rest parameter gets a list of the positional arguments left over.
+    pub fn match_arguments(
+        &self,
+        positional: Vec<Object>,
+        named: Vec<(Token, Object)>,
+        paren: &Token,
+    ) -> Result<Vec<Option<Object>>, RuntimeError> {
+        let rest = self.params.last().is_some_and(|param| param.rest);
+        let fixed = self.params.len() - rest as usize;
+        let required = self
+            .params
+            .iter()
+            .filter(|param| param.default.is_none() && !param.rest)
+            .count();
+
+        let count = positional.len();
+        if (count > fixed && !rest) || (named.is_empty() && count < required) {
+            return Err(arity_error(required, fixed, rest, count, paren));
+        }
+
+        let mut values: Vec<Option<Object>> = vec![None; self.params.len()];
+        let mut positional = positional.into_iter();
+        for value in values.iter_mut().take(fixed) {
+            *value = positional.next();
+        }
+        if rest {
+            let rest = positional.collect();
+            values[fixed] = Some(Object::List(Rc::new(RefCell::new(rest))));
+        }
+
+        for (name, value) in named {
+            let index = self.params[..fixed]
+                .iter()
+                .position(|param| param.name.lexeme == name.lexeme);
+            let Some(index) = index else {
+                return Err(RuntimeError::new(
+                    name.clone(),
+                    format!("Unexpected argument '{}'.", name.lexeme),
+                ));
+            };
+            if values[index].is_some() {
+                return Err(RuntimeError::new(
+                    name.clone(),
+                    format!("Got multiple values for argument '{}'.", name.lexeme),
+                ));
+            }
+            values[index] = Some(value);
+        }
+
+        let missing: Vec<String> = self
+            .params
+            .iter()
+            .zip(&values)
+            .filter(|(param, value)| value.is_none() && param.default.is_none())
+            .map(|(param, _)| format!("'{}'", param.name.lexeme))
+            .collect();
+        if !missing.is_empty() {
+            let plural = if missing.len() == 1 { "" } else { "s" };
+            return Err(RuntimeError::new(
+                paren.clone(),
+                format!("Missing argument{plural} {}.", missing.join(", ")),
+            ));
+        }
+        Ok(values)
+    }
+}
+
+/// Describes how many positional arguments a function takes, for example
+/// "Expected 1 to 3 arguments but got 4."
+fn arity_error(
+    required: usize,
+    fixed: usize,
+    rest: bool,
+    count: usize,
+    paren: &Token,
+) -> RuntimeError {
+    let expected = if rest {
+        format!("at least {required}")
+    } else if required == fixed {
+        required.to_string()
+    } else {
+        format!("{required} to {fixed}")
+    };
+    RuntimeError::new(
+        paren.clone(),
+        format!("Expected {expected} arguments but got {count}."),
+    )
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "<fn {name}>"),
+            None => write!(f, "<fn>"),
+        }
+    }
+}
+
+/// Functions are only equal to themselves.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Closure) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,128 @@
+use core::fmt;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// A handle to an interned string. Two symbols are equal exactly when the
+/// strings they were interned from are equal, so comparing and hashing a
+/// symbol never has to look at the underlying characters.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+struct Interner {
+    map: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.map.get(name) {
+            return symbol;
+        }
+        // interned strings live for the rest of the program, so leaking them
+        // lets `Symbol::as_str` hand out plain `&'static str`s
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        let symbol = Symbol(self.strings.len() as u32);
+        self.map.insert(name, symbol);
+        self.strings.push(name);
+        symbol
+    }
+
+    fn lookup(&self, symbol: Symbol) -> &'static str {
+        self.strings[symbol.0 as usize]
+    }
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        INTERNER.with(|interner| interner.borrow_mut().intern(name))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        INTERNER.with(|interner| interner.borrow().lookup(self))
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+/// A string value. Those written as literals in the source are interned, so
+/// comparing two of them never looks at their characters; those built at run
+/// time come and go, so they are only reference counted.
+#[derive(Clone)]
+pub enum LoxString {
+    Interned(Symbol),
+    Owned(Rc<str>),
+}
+
+impl LoxString {
+    pub fn as_str(&self) -> &str {
+        match self {
+            LoxString::Interned(symbol) => symbol.as_str(),
+            LoxString::Owned(string) => string,
+        }
+    }
+}
+
+impl Deref for LoxString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for LoxString {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LoxString::Interned(a), LoxString::Interned(b)) => a == b,
+            _ => self.as_str() == other.as_str(),
+        }
+    }
+}
+
+impl Hash for LoxString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl From<&str> for LoxString {
+    fn from(string: &str) -> Self {
+        LoxString::Owned(Rc::from(string))
+    }
+}
+
+impl From<String> for LoxString {
+    fn from(string: String) -> Self {
+        LoxString::Owned(Rc::from(string))
+    }
+}
+
+impl fmt::Display for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
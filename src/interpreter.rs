@@ -1,152 +1,847 @@
+use std::{cell::RefCell, cmp::Ordering, collections::HashSet, fmt, rc::Rc};
+
+use indexmap::{IndexMap, IndexSet};
+
 use crate::{
-    ast::{Expr, Stmt},
+    ast::{Call, Expr, Param, Stmt},
+    builtins::{self, ErrorObject, Range},
+    environment::Environment,
+    function::Closure,
+    interner::{LoxString, Symbol},
+    iterator::ObjectIter,
+    map::MapKey,
+    number::{self, NumberError},
     scanner::{Object, Token, TokenType},
-    Lox,
 };
 
+/// One call in a stack trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFrame {
+    /// The function that was running, or `None` for the top level of the
+    /// script.
+    pub function: Option<Symbol>,
+    pub file: Symbol,
+    pub line: i32,
+    pub column: i32,
+}
+
+impl fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}:{}:{}] in ", self.file, self.line, self.column)?;
+        match self.function {
+            Some(name) => write!(f, "{name}()"),
+            None => write!(f, "script"),
+        }
+    }
+}
+
+/// An error raised while running code. The token and thrown value are boxed
+/// to keep the `Result`s carrying errors through the interpreter small.
 pub struct RuntimeError {
-    pub token: Token,
+    pub token: Box<Token>,
     pub message: String,
+    /// The value given to `throw`, or `None` for an error raised by the
+    /// interpreter itself.
+    pub value: Option<Box<Object>>,
+    /// The calls that were active when the error happened, innermost first.
+    /// Empty until the error is traced on its way out of the code that
+    /// raised it.
+    pub stack: Vec<StackFrame>,
 }
 
 impl RuntimeError {
-    fn new(token: Token, message: String) -> RuntimeError {
-        RuntimeError { token, message }
+    pub fn new(token: Token, message: String) -> RuntimeError {
+        RuntimeError {
+            token: Box::new(token),
+            message,
+            value: None,
+            stack: vec![],
+        }
+    }
+
+    /// The error for `throw value`. Throwing a caught error again keeps its
+    /// original message and stack trace.
+    fn thrown(keyword: Token, value: Object) -> RuntimeError {
+        let (message, stack) = match &value {
+            Object::Error(error) => (error.message.clone(), error.stack.clone()),
+            value => (stringify(value.clone()), vec![]),
+        };
+        RuntimeError {
+            token: Box::new(keyword),
+            message,
+            value: Some(Box::new(value)),
+            stack,
+        }
+    }
+
+    /// The value a `catch` clause binds: whatever was thrown, or an error
+    /// object describing a built-in error.
+    fn into_object(self) -> Object {
+        match self.value {
+            Some(value) => *value,
+            None => Object::Error(Rc::new(ErrorObject {
+                message: self.message,
+                line: self.token.line,
+                stack: self.stack,
+            })),
+        }
     }
 }
 
-fn evaluate(expr: Expr) -> Result<Object, RuntimeError> {
-    match expr {
-        Expr::Binary(e) => {
-            let left = evaluate(*e.left)?;
-            let right = evaluate(*e.right)?;
+/// How a statement finished. Anything other than `Normal` unwinds the
+/// enclosing statements until a loop or function call handles it.
+pub enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Object),
+    /// Returns the result of a call that hasn't been made yet, so that the
+    /// function returning can make it in its own frame.
+    TailCall(Box<PendingCall>),
+}
 
-            match e.operator.token_type {
-                TokenType::Minus => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::F64(left_value - right_value)),
-                        _ => Err(number_operands_error(e.operator)),
-                    },
-                    _ => Err(number_operands_error(e.operator)),
-                },
-                TokenType::Slash => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::F64(left_value / right_value)),
-                        _ => Err(number_operands_error(e.operator)),
-                    },
-                    _ => Err(number_operands_error(e.operator)),
-                },
-                TokenType::Star => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::F64(left_value * right_value)),
-                        _ => Err(number_operands_error(e.operator)),
-                    },
-                    _ => Err(number_operands_error(e.operator)),
-                },
-                TokenType::Plus => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::F64(left_value + right_value)),
-                        _ => Err(addition_operands_error(e.operator)),
-                    },
-                    Object::Str(left_value) => match right {
-                        Object::Str(right_value) => {
-                            Ok(Object::Str(format!("{}{}", left_value, right_value)))
+/// A call whose callee and arguments have been evaluated.
+pub struct PendingCall {
+    callee: Object,
+    arguments: Vec<Object>,
+    named_arguments: Vec<(Token, Object)>,
+    paren: Token,
+}
+
+pub struct Interpreter {
+    environment: Rc<RefCell<Environment>>,
+    /// The file being run, for stack traces.
+    file: Symbol,
+    /// The active function calls, outermost first: each function's name and
+    /// the token where it was called.
+    calls: Vec<(Symbol, Token)>,
+    /// How many statements and expressions are being run, each inside the
+    /// next. Only calls can nest them further than the parser allows, so
+    /// that's where this is checked against `max_depth`.
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Interpreter {
+    pub fn new(file: &str, max_depth: usize) -> Interpreter {
+        let mut globals = Environment::new(None);
+        for function in builtins::functions() {
+            globals.define_builtin(function.name, Object::BuiltinFunction(Rc::new(function)));
+        }
+        globals.define_builtin(Symbol::intern("done"), Object::Done);
+        Interpreter {
+            environment: Rc::new(RefCell::new(globals)),
+            file: Symbol::intern(file),
+            calls: vec![],
+            depth: 0,
+            max_depth,
+        }
+    }
+
+    /// Records the active calls in `error` if it hasn't been traced yet. This
+    /// has to happen before any of those calls return.
+    fn trace(&self, error: &mut RuntimeError) {
+        if !error.stack.is_empty() {
+            return;
+        }
+        let mut stack = vec![];
+        let mut token: &Token = &error.token;
+        for (name, call_site) in self.calls.iter().rev() {
+            stack.push(self.frame(Some(*name), token));
+            token = call_site;
+        }
+        stack.push(self.frame(None, token));
+        error.stack = stack;
+    }
+
+    fn frame(&self, function: Option<Symbol>, token: &Token) -> StackFrame {
+        StackFrame {
+            function,
+            file: self.file,
+            line: token.line,
+            column: token.column,
+        }
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Object, RuntimeError> {
+        self.depth += 1;
+        let result = self.evaluate_expr(expr);
+        self.depth -= 1;
+        result
+    }
+
+    fn evaluate_expr(&mut self, expr: &Expr) -> Result<Object, RuntimeError> {
+        match expr {
+            Expr::Assign(e) => {
+                let value = self.evaluate(&e.value)?;
+                self.environment
+                    .borrow_mut()
+                    .assign(&e.name, value.clone())?;
+                Ok(value)
+            }
+            Expr::Binary(e) => {
+                let left = self.evaluate(&e.left)?;
+                let right = self.evaluate(&e.right)?;
+
+                let operator = &e.operator;
+                match operator.token_type {
+                    TokenType::Minus | TokenType::Ampersand | TokenType::Pipe
+                        if matches!(left, Object::Set(_)) || matches!(right, Object::Set(_)) =>
+                    {
+                        return set_operation(operator, left, right)
+                    }
+                    TokenType::DotDot | TokenType::DotDotEqual => {
+                        return range(operator, left, right)
+                    }
+                    TokenType::Minus => number::subtract(left, right),
+                    TokenType::Slash => number::divide(left, right),
+                    TokenType::Star => number::multiply(left, right),
+                    TokenType::Percent => number::modulo(left, right),
+                    TokenType::TildeSlash => number::floor_divide(left, right),
+                    TokenType::StarStar => number::power(left, right),
+                    TokenType::Plus => match (&left, &right) {
+                        (Object::Str(left_value), Object::Str(right_value)) => {
+                            return Ok(Object::Str(LoxString::from(format!(
+                                "{}{}",
+                                left_value, right_value
+                            ))))
+                        }
+                        _ => {
+                            return number::add(left, right).map_err(|error| match error {
+                                NumberError::NotNumber => addition_operands_error(operator.clone()),
+                                error => number_error(operator.clone(), error),
+                            })
                         }
-                        _ => Err(addition_operands_error(e.operator)),
-                    },
-                    _ => Err(addition_operands_error(e.operator)),
-                },
-                TokenType::Greater => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::Bool(left_value > right_value)),
-                        _ => Err(number_operands_error(e.operator)),
-                    },
-                    _ => Err(number_operands_error(e.operator)),
-                },
-                TokenType::GreaterEqual => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::Bool(left_value >= right_value)),
-                        _ => Err(number_operands_error(e.operator)),
-                    },
-                    _ => Err(number_operands_error(e.operator)),
-                },
-                TokenType::Less => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::Bool(left_value < right_value)),
-                        _ => Err(number_operands_error(e.operator)),
-                    },
-                    _ => Err(number_operands_error(e.operator)),
-                },
-                TokenType::LessEqual => match left {
-                    Object::F64(left_value) => match right {
-                        Object::F64(right_value) => Ok(Object::Bool(left_value <= right_value)),
-                        _ => Err(number_operands_error(e.operator)),
                     },
-                    _ => Err(number_operands_error(e.operator)),
-                },
-                TokenType::BangEqual => Ok(Object::Bool(!is_equal(left, right))),
-                TokenType::EqualEqual => Ok(Object::Bool(is_equal(left, right))),
-                _ => Ok(Object::Nil), // unreachable
+                    TokenType::Greater => {
+                        compare(left, right, |ordering| ordering == Ordering::Greater)
+                    }
+                    TokenType::GreaterEqual => {
+                        compare(left, right, |ordering| ordering != Ordering::Less)
+                    }
+                    TokenType::Less => compare(left, right, |ordering| ordering == Ordering::Less),
+                    TokenType::LessEqual => {
+                        compare(left, right, |ordering| ordering != Ordering::Greater)
+                    }
+                    TokenType::Ampersand => number::bit_and(left, right),
+                    TokenType::Pipe => number::bit_or(left, right),
+                    TokenType::Caret => number::bit_xor(left, right),
+                    TokenType::LessLess => number::shift_left(left, right),
+                    TokenType::GreaterGreater => number::shift_right(left, right),
+                    TokenType::Comma => return Ok(right),
+                    TokenType::BangEqual => return Ok(Object::Bool(!is_equal(left, right))),
+                    TokenType::EqualEqual => return Ok(Object::Bool(is_equal(left, right))),
+                    _ => return Ok(Object::Nil), // unreachable
+                }
+                .map_err(|error| number_error(operator.clone(), error))
+            }
+            Expr::Call(e) => {
+                let call = self.evaluate_call(e)?;
+                match call.callee {
+                    Object::Closure(closure) => {
+                        self.call(closure, call.arguments, call.named_arguments, call.paren)
+                    }
+                    callee => {
+                        call_builtin(callee, call.arguments, call.named_arguments, call.paren)
+                    }
+                }
+            }
+            Expr::Conditional(e) => {
+                if is_truthy(self.evaluate(&e.condition)?) {
+                    self.evaluate(&e.then_branch)
+                } else {
+                    self.evaluate(&e.else_branch)
+                }
+            }
+            Expr::Function(e) => Ok(Object::Closure(Rc::new(Closure {
+                name: e.name.as_ref().map(|name| name.lexeme.symbol()),
+                params: e.params.clone(),
+                body: e.body.clone(),
+                environment: self.environment.clone(),
+            }))),
+            Expr::Get(e) => {
+                let object = self.evaluate(&e.object)?;
+                if let Some(value) = builtins::get_property(&object, e.name.lexeme.symbol()) {
+                    return Ok(value);
+                }
+                match builtins::get_method(object, e.name.lexeme.symbol()) {
+                    Some(method) => Ok(Object::BuiltinMethod(Rc::new(method))),
+                    None => Err(RuntimeError::new(
+                        e.name.clone(),
+                        format!("Undefined property '{}'.", e.name.lexeme),
+                    )),
+                }
+            }
+            Expr::Grouping(e) => self.evaluate(&e.expression),
+            Expr::Index(e) => {
+                let object = self.evaluate(&e.object)?;
+                let index = self.evaluate(&e.index)?;
+                match object {
+                    Object::List(list) => {
+                        let list = list.borrow();
+                        let index = builtins::list_index(&index, list.len(), &e.bracket)?;
+                        Ok(list[index].clone())
+                    }
+                    Object::Tuple(tuple) => {
+                        let index = builtins::list_index(&index, tuple.len(), &e.bracket)?;
+                        Ok(tuple[index].clone())
+                    }
+                    Object::Map(map) => {
+                        let key = MapKey::new(index, &e.bracket)?;
+                        let value = map.borrow().get(&key).cloned();
+                        value.ok_or_else(|| undefined_key_error(e.bracket.clone(), key))
+                    }
+                    _ => Err(index_operand_error(e.bracket.clone())),
+                }
             }
+            Expr::Interpolation(e) => {
+                let mut value = String::new();
+                for part in &e.parts {
+                    value.push_str(&stringify(self.evaluate(part)?));
+                }
+                Ok(Object::Str(LoxString::from(value)))
+            }
+            Expr::List(e) => {
+                let mut elements = vec![];
+                for element in &e.elements {
+                    elements.push(self.evaluate(element)?);
+                }
+                Ok(Object::List(Rc::new(RefCell::new(elements))))
+            }
+            Expr::Literal(e) => Ok(e.value.clone()),
+            Expr::Map(e) => {
+                let mut map = IndexMap::new();
+                for (key, value) in &e.entries {
+                    let key = MapKey::new(self.evaluate(key)?, &e.brace)?;
+                    map.insert(key, self.evaluate(value)?);
+                }
+                Ok(Object::Map(Rc::new(RefCell::new(map))))
+            }
+            Expr::SetIndex(e) => {
+                let object = self.evaluate(&e.object)?;
+                let index = self.evaluate(&e.index)?;
+                let value = self.evaluate(&e.value)?;
+                match object {
+                    Object::List(list) => {
+                        let mut list = list.borrow_mut();
+                        let index = builtins::list_index(&index, list.len(), &e.bracket)?;
+                        list[index] = value.clone();
+                    }
+                    Object::Map(map) => {
+                        let key = MapKey::new(index, &e.bracket)?;
+                        map.borrow_mut().insert(key, value.clone());
+                    }
+                    Object::Tuple(_) => {
+                        return Err(RuntimeError::new(
+                            e.bracket.clone(),
+                            "Tuples are immutable.".to_owned(),
+                        ))
+                    }
+                    _ => return Err(index_operand_error(e.bracket.clone())),
+                }
+                Ok(value)
+            }
+            Expr::Set(e) => {
+                let mut set = IndexSet::new();
+                for element in &e.elements {
+                    set.insert(MapKey::new(self.evaluate(element)?, &e.brace)?);
+                }
+                Ok(Object::Set(Rc::new(RefCell::new(set))))
+            }
+            Expr::Slice(e) => {
+                let object = self.evaluate(&e.object)?;
+                let start = e
+                    .start
+                    .as_ref()
+                    .map(|start| self.evaluate(start))
+                    .transpose()?;
+                let end = e.end.as_ref().map(|end| self.evaluate(end)).transpose()?;
+                match object {
+                    Object::List(list) => {
+                        let elements = slice(&list.borrow(), start, end, &e.bracket)?;
+                        Ok(Object::List(Rc::new(RefCell::new(elements))))
+                    }
+                    Object::Tuple(tuple) => {
+                        let elements = slice(&tuple, start, end, &e.bracket)?;
+                        Ok(Object::Tuple(Rc::new(elements)))
+                    }
+                    _ => Err(RuntimeError::new(
+                        e.bracket.clone(),
+                        "Only lists and tuples can be sliced.".to_owned(),
+                    )),
+                }
+            }
+            Expr::Tuple(e) => {
+                let mut elements = vec![];
+                for element in &e.elements {
+                    elements.push(self.evaluate(element)?);
+                }
+                Ok(Object::Tuple(Rc::new(elements)))
+            }
+            Expr::Unary(e) => {
+                let right = self.evaluate(&e.right)?;
+
+                match e.operator.token_type {
+                    TokenType::Minus => {
+                        number::negate(right).map_err(|_| number_operand_error(e.operator.clone()))
+                    }
+                    TokenType::Bang => Ok(Object::Bool(!is_truthy(right))),
+                    TokenType::Tilde => number::bit_not(right)
+                        .map_err(|_| integer_operand_error(e.operator.clone())),
+                    _ => Ok(Object::Nil), // unreachable
+                }
+            }
+            Expr::Variable(e) => self.environment.borrow().get(&e.name),
         }
-        Expr::Grouping(e) => evaluate(*e.expression),
-        Expr::Literal(e) => Ok(e.value),
-        Expr::Unary(e) => {
-            let right = evaluate(*e.right)?;
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<Flow, RuntimeError> {
+        self.depth += 1;
+        let result = self.execute_stmt(stmt);
+        self.depth -= 1;
+        result
+    }
 
-            match e.operator.token_type {
-                TokenType::Minus => match right {
-                    Object::F64(value) => Ok(Object::F64(-value)),
-                    _ => Err(number_operand_error(e.operator)),
-                },
-                TokenType::Bang => Ok(Object::Bool(is_truthy(right))),
-                _ => Ok(Object::Nil), // unreachable
+    fn execute_stmt(&mut self, stmt: &Stmt) -> Result<Flow, RuntimeError> {
+        match stmt {
+            Stmt::Block(s) => {
+                let environment = Environment::new(Some(self.environment.clone()));
+                return self.execute_block(&s.statements, environment);
             }
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Continue => return Ok(Flow::Continue),
+            Stmt::Expression(s) => {
+                self.evaluate(&s.expression)?;
+            }
+            Stmt::ForIn(s) => {
+                let iterable = self.evaluate(&s.iterable)?;
+                let mut iter = ObjectIter::new(iterable, &s.keyword, |iter| {
+                    self.call_value(iter, &s.keyword)
+                })?;
+                while let Some(value) = iter.next(|next| self.call_value(next, &s.keyword))? {
+                    // each pass gets a fresh variable
+                    let mut environment = Environment::new(Some(self.environment.clone()));
+                    environment.define(&s.name, value)?;
+                    match self.execute_block(std::slice::from_ref(&*s.body), environment)? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => (),
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+            Stmt::If(s) => {
+                if is_truthy(self.evaluate(&s.condition)?) {
+                    return self.execute(&s.then_branch);
+                } else if let Some(else_branch) = &s.else_branch {
+                    return self.execute(else_branch);
+                }
+            }
+            Stmt::Print(s) => {
+                let value = self.evaluate(&s.expression)?;
+                println!("{}", stringify(value))
+            }
+            Stmt::Return(s) => {
+                return match &s.value {
+                    Some(value) => self.evaluate_tail(value),
+                    None => Ok(Flow::Return(Object::Nil)),
+                };
+            }
+            Stmt::Throw(s) => {
+                let value = self.evaluate(&s.value)?;
+                return Err(RuntimeError::thrown(s.keyword.clone(), value));
+            }
+            Stmt::Try(s) => {
+                // a tail call can't leave the statement before the catch and
+                // finally blocks have seen how it finished
+                let environment = Environment::new(Some(self.environment.clone()));
+                let mut result = self.execute_block(&s.body, environment);
+                result = self.finish_tail_call(result);
+                if let Some(catch) = &s.catch {
+                    result = match result {
+                        Err(mut error) => {
+                            self.trace(&mut error);
+                            let mut environment = Environment::new(Some(self.environment.clone()));
+                            environment.define(&catch.name, error.into_object())?;
+                            self.execute_block(&catch.body, environment)
+                        }
+                        result => result,
+                    };
+                }
+                if let Some(finally) = &s.finally {
+                    result = self.finish_tail_call(result);
+                    // a finally block that fails or jumps out overrides how the
+                    // rest of the statement finished
+                    let environment = Environment::new(Some(self.environment.clone()));
+                    match self.execute_block(finally, environment)? {
+                        Flow::Normal => (),
+                        flow => return Ok(flow),
+                    }
+                }
+                return result;
+            }
+            Stmt::Var(s) => {
+                let value = match &s.initializer {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => Object::Nil,
+                };
+                let mut environment = self.environment.borrow_mut();
+                if s.constant {
+                    environment.define_constant(&s.name, value)?;
+                } else {
+                    environment.define(&s.name, value)?;
+                }
+            }
+            Stmt::While(s) => {
+                while is_truthy(self.evaluate(&s.condition)?) {
+                    match self.execute(&s.body)? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => (),
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+        };
+        Ok(Flow::Normal)
+    }
+
+    /// Evaluates the value of a `return`. A call to a function in tail
+    /// position, which includes either branch of a conditional, is left to
+    /// the function returning, which reuses its frame for it.
+    fn evaluate_tail(&mut self, expr: &Expr) -> Result<Flow, RuntimeError> {
+        match expr {
+            Expr::Call(e) => {
+                let call = self.evaluate_call(e)?;
+                match call.callee {
+                    Object::Closure(_) => Ok(Flow::TailCall(Box::new(call))),
+                    callee => {
+                        call_builtin(callee, call.arguments, call.named_arguments, call.paren)
+                            .map(Flow::Return)
+                    }
+                }
+            }
+            Expr::Conditional(e) => {
+                if is_truthy(self.evaluate(&e.condition)?) {
+                    self.evaluate_tail(&e.then_branch)
+                } else {
+                    self.evaluate_tail(&e.else_branch)
+                }
+            }
+            Expr::Grouping(e) => self.evaluate_tail(&e.expression),
+            expr => self.evaluate(expr).map(Flow::Return),
         }
     }
-}
 
-fn execute(stmt: Stmt) -> Result<(), RuntimeError> {
-    match stmt {
-        Stmt::Expression(s) => {
-            evaluate(s.expression)?;
-            ()
+    /// Evaluates the callee and arguments of a call, in that order.
+    fn evaluate_call(&mut self, e: &Call) -> Result<PendingCall, RuntimeError> {
+        let callee = self.evaluate(&e.callee)?;
+        let mut arguments = vec![];
+        for argument in &e.arguments {
+            arguments.push(self.evaluate(argument)?);
         }
-        Stmt::Print(s) => {
-            let value = evaluate(s.expression)?;
-            println!("{}", stringify(value))
+        let mut named_arguments = vec![];
+        for (name, argument) in &e.named_arguments {
+            named_arguments.push((name.clone(), self.evaluate(argument)?));
         }
-    };
-    Ok(())
-}
+        Ok(PendingCall {
+            callee,
+            arguments,
+            named_arguments,
+            paren: e.paren.clone(),
+        })
+    }
 
-pub fn interpret(statements: Vec<Stmt>, lox: &mut Lox) {
-    for stmt in statements {
-        match execute(stmt) {
-            Ok(_) => (),
-            Err(error) => {
-                lox.runtime_error(error);
-                return;
+    /// Calls `closure`. Tail calls made by its body replace it in the same
+    /// frame instead of nesting, so recursion in tail position runs in
+    /// constant stack space.
+    /// Calls `callee` with no arguments, as a `for` loop does the functions
+    /// of the iterator protocol.
+    fn call_value(&mut self, callee: Object, token: &Token) -> Result<Object, RuntimeError> {
+        match callee {
+            Object::Closure(closure) => self.call(closure, vec![], vec![], token.clone()),
+            callee => call_builtin(callee, vec![], vec![], token.clone()),
+        }
+    }
+
+    fn call(
+        &mut self,
+        closure: Rc<Closure>,
+        arguments: Vec<Object>,
+        named_arguments: Vec<(Token, Object)>,
+        paren: Token,
+    ) -> Result<Object, RuntimeError> {
+        if self.depth >= self.max_depth {
+            return Err(RuntimeError::new(paren, "Stack overflow.".to_owned()));
+        }
+        let mut values = closure.match_arguments(arguments, named_arguments, &paren)?;
+        let mut closure = closure;
+        self.calls.push((function_name(&closure), paren));
+        let mut result = loop {
+            let environment = Environment::new(Some(closure.environment.clone()));
+            let previous =
+                std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+            let result = self
+                .bind_parameters(&closure.params, values)
+                .and_then(|()| self.execute_statements(&closure.body));
+            self.environment = previous;
+
+            let call = match result {
+                Ok(Flow::TailCall(call)) => *call,
+                result => break result,
+            };
+            let Object::Closure(callee) = call.callee else {
+                unreachable!("only functions are called in tail position");
+            };
+            // arity errors belong to the caller, which is still on the stack
+            match callee.match_arguments(call.arguments, call.named_arguments, &call.paren) {
+                Ok(next) => values = next,
+                Err(error) => break Err(error),
+            }
+            closure = callee;
+            // the callee returns to where the caller was called from
+            if let Some((name, _)) = self.calls.last_mut() {
+                *name = function_name(&closure);
             }
+        };
+        if let Err(error) = &mut result {
+            self.trace(error);
         }
+        self.calls.pop();
+
+        match result? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Object::Nil),
+        }
+    }
+
+    /// Makes the call a statement ended with, if it ended with a tail call,
+    /// for statements that still have work to do once the call returns.
+    fn finish_tail_call(
+        &mut self,
+        result: Result<Flow, RuntimeError>,
+    ) -> Result<Flow, RuntimeError> {
+        match result {
+            Ok(Flow::TailCall(call)) => {
+                let Object::Closure(closure) = call.callee else {
+                    unreachable!("only functions are called in tail position");
+                };
+                self.call(closure, call.arguments, call.named_arguments, call.paren)
+                    .map(Flow::Return)
+            }
+            result => result,
+        }
+    }
+
+    /// Defines the parameters of a call in the current environment, using
+    /// their defaults where `match_arguments` found no value. Defaults are
+    /// evaluated in order, so they can refer to earlier parameters.
+    fn bind_parameters(
+        &mut self,
+        params: &[Param],
+        values: Vec<Option<Object>>,
+    ) -> Result<(), RuntimeError> {
+        for (param, value) in params.iter().zip(values) {
+            let value = match (value, &param.default) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.evaluate(default)?,
+                (None, None) => unreachable!("missing arguments are reported"),
+            };
+            self.environment.borrow_mut().define(&param.name, value)?;
+        }
+        Ok(())
+    }
+
+    /// Runs `statements` in `environment`, restoring the current environment
+    /// afterwards even if one of them fails.
+    fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<Flow, RuntimeError> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = self.execute_statements(statements);
+        self.environment = previous;
+        result
     }
+
+    /// Runs `statements` in the current environment. Stops early at a
+    /// statement that doesn't finish normally and passes on how it finished.
+    fn execute_statements(&mut self, statements: &[Stmt]) -> Result<Flow, RuntimeError> {
+        for stmt in statements {
+            match self.execute(stmt)? {
+                Flow::Normal => (),
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), RuntimeError> {
+        for stmt in statements {
+            // the parser rejects `break` and `continue` outside of loops
+            if let Err(mut error) = self.execute(&stmt) {
+                self.trace(&mut error);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A step in writing out a value. Collections nest far deeper than the
+/// native stack allows, so `stringify` keeps the steps still to come on a
+/// stack of its own instead of recursing.
+enum Write {
+    /// A value, quoted if it's a string inside a collection.
+    Value(Object, bool),
+    Text(&'static str),
+    /// Finishes a list or map, so that it can be written again.
+    Leave(*const ()),
 }
 
 fn stringify(object: Object) -> String {
+    write_value(object, false)
+}
+
+/// Like `stringify`, but quotes strings so they stand out inside a collection.
+fn stringify_element(object: Object) -> String {
+    write_value(object, true)
+}
+
+fn write_value(object: Object, quoted: bool) -> String {
+    let mut text = String::new();
+    let mut steps = vec![Write::Value(object, quoted)];
+    // lists and maps being written further out, which one that contains
+    // itself leads back to
+    let mut writing = HashSet::new();
+    while let Some(step) = steps.pop() {
+        let object = match step {
+            Write::Value(object, quoted) => match object {
+                Object::Str(value) if quoted => {
+                    text.push_str(&format!("{value:?}"));
+                    continue;
+                }
+                object => object,
+            },
+            Write::Text(part) => {
+                text.push_str(part);
+                continue;
+            }
+            Write::Leave(collection) => {
+                writing.remove(&collection);
+                continue;
+            }
+        };
+        match object {
+            Object::List(list) => {
+                let pointer = Rc::as_ptr(&list) as *const ();
+                if !writing.insert(pointer) {
+                    text.push_str("[...]");
+                    continue;
+                }
+                let elements = list
+                    .borrow()
+                    .iter()
+                    .map(|element| vec![element.clone()])
+                    .collect();
+                steps.push(Write::Leave(pointer));
+                push_collection(&mut steps, "[", elements, "]");
+            }
+            Object::Tuple(tuple) => {
+                let elements = tuple.iter().map(|element| vec![element.clone()]).collect();
+                let close = if tuple.len() == 1 { ",)" } else { ")" };
+                push_collection(&mut steps, "(", elements, close);
+            }
+            Object::Set(set) => {
+                let set = set.borrow();
+                if set.is_empty() {
+                    // `{}` would read back as an empty map
+                    text.push_str("{,}");
+                    continue;
+                }
+                let elements = set.iter().map(|element| vec![element.0.clone()]).collect();
+                push_collection(&mut steps, "{", elements, "}");
+            }
+            Object::Map(map) => {
+                let pointer = Rc::as_ptr(&map) as *const ();
+                if !writing.insert(pointer) {
+                    text.push_str("{...}");
+                    continue;
+                }
+                let entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| vec![key.0.clone(), value.clone()])
+                    .collect();
+                steps.push(Write::Leave(pointer));
+                push_collection(&mut steps, "{", entries, "}");
+            }
+            object => text.push_str(&stringify_scalar(object)),
+        }
+    }
+    text
+}
+
+/// Queues the steps that write out a collection's `items` between `open` and
+/// `close`. Each item is one element, or a key and value.
+fn push_collection(
+    steps: &mut Vec<Write>,
+    open: &'static str,
+    items: Vec<Vec<Object>>,
+    close: &'static str,
+) {
+    steps.push(Write::Text(close));
+    for (index, item) in items.into_iter().enumerate().rev() {
+        let mut parts = item.into_iter().rev().peekable();
+        while let Some(part) = parts.next() {
+            steps.push(Write::Value(part, true));
+            if parts.peek().is_some() {
+                steps.push(Write::Text(": "));
+            }
+        }
+        if index > 0 {
+            steps.push(Write::Text(", "));
+        }
+    }
+    steps.push(Write::Text(open));
+}
+
+/// Writes out anything but a collection.
+fn stringify_scalar(object: Object) -> String {
     match object {
-        Object::Str(value) => value,
-        Object::F64(value) => match value.to_string().strip_suffix(".0") {
-            // strip the trailing .0 if value is an integer
-            Some(s) => s.to_owned(),
-            None => value.to_string(),
-        },
+        Object::Str(value) => value.to_string(),
+        Object::Int(value) => value.to_string(),
+        Object::BigInt(value) => value.to_string(),
+        Object::Decimal(value) => value.to_plain_string(),
+        // unlike `Display`, `Debug` keeps the ".0" on integral floats
+        Object::F64(value) => format!("{value:?}"),
 
         Object::Bool(value) => value.to_string(),
         Object::Nil => "nil".to_owned(),
+        Object::Range(range) => {
+            let operator = if range.inclusive { "..=" } else { ".." };
+            format!("{}{}{}", range.start, operator, range.end)
+        }
+        Object::Error(error) => format!("Error: {}", error.message),
+        Object::Closure(closure) => format!("{closure:?}"),
+        Object::BuiltinMethod(method) => format!("<native method {}>", method.name),
+        Object::BuiltinFunction(function) => format!("<native fn {}>", function.name),
+        Object::Done => "done".to_owned(),
+        Object::List(_) | Object::Tuple(_) | Object::Set(_) | Object::Map(_) => {
+            unreachable!("collections are written by `write_value`")
+        }
+    }
+}
+
+/// Applies a comparison operator, whose result is decided by `test` unless
+/// the operands are unordered.
+fn compare(left: Object, right: Object, test: fn(Ordering) -> bool) -> Result<Object, NumberError> {
+    match number::compare(&left, &right) {
+        Some(ordering) => Ok(Object::Bool(ordering.is_some_and(test))),
+        None => Err(NumberError::NotNumber),
     }
 }
 
-fn is_truthy(object: Object) -> bool {
+pub fn is_truthy(object: Object) -> bool {
     match object {
         Object::Bool(value) => value,
         Object::Nil => false,
@@ -154,27 +849,232 @@ fn is_truthy(object: Object) -> bool {
     }
 }
 
-fn is_equal(a: Object, b: Object) -> bool {
+pub fn is_equal(a: Object, b: Object) -> bool {
+    // pairs of collections still to compare. Nested collections are queued
+    // here rather than compared recursively, since they can nest far deeper
+    // than the native stack allows.
+    let mut pending = vec![(a, b)];
+    // pairs of lists or maps already queued. A list or map that contains
+    // itself leads back to one of these, and is taken to be equal to its
+    // partner there, since any difference between them is found by the
+    // comparison already queued.
+    let mut compared = HashSet::new();
+    while let Some((a, b)) = pending.pop() {
+        if !equal_shallow(a, b, &mut pending, &mut compared) {
+            return false;
+        }
+    }
+    true
+}
+
+/// The pointers of a pair of lists or maps being compared.
+type Comparison = (*const (), *const ());
+
+/// Compares `a` and `b` as `==` does, except that the elements of two
+/// collections are pushed onto `pending` rather than compared here.
+fn equal_shallow(
+    a: Object,
+    b: Object,
+    pending: &mut Vec<(Object, Object)>,
+    compared: &mut HashSet<Comparison>,
+) -> bool {
     match a {
-        Object::Nil => match b {
-            Object::Nil => true,
+        Object::Nil => matches!(b, Object::Nil),
+        Object::List(a) => match b {
+            Object::List(b) if Rc::ptr_eq(&a, &b) => true,
+            Object::List(b) => {
+                let pair = (Rc::as_ptr(&a) as *const (), Rc::as_ptr(&b) as *const ());
+                !compared.insert(pair) || elements_equal(&a.borrow(), &b.borrow(), pending)
+            }
+            _ => false,
+        },
+        Object::Tuple(a) => match b {
+            Object::Tuple(b) => elements_equal(&a, &b, pending),
+            _ => false,
+        },
+        Object::Set(a) => match b {
+            Object::Set(b) if Rc::ptr_eq(&a, &b) => true,
+            Object::Set(b) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().all(|element| b.contains(element))
+            }
             _ => false,
         },
+        // ranges are equal when they hold the same integers
+        Object::Range(a) => match b {
+            Object::Range(b) => a.len() == b.len() && (a.len() == 0 || a.start == b.start),
+            _ => false,
+        },
+        Object::Map(a) => match b {
+            Object::Map(b) if Rc::ptr_eq(&a, &b) => true,
+            // maps are equal when they hold the same entries in any order
+            Object::Map(b) => {
+                let pair = (Rc::as_ptr(&a) as *const (), Rc::as_ptr(&b) as *const ());
+                if !compared.insert(pair) {
+                    return true;
+                }
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len()
+                    && a.iter().all(|(key, a)| match b.get(key) {
+                        Some(b) => {
+                            pending.push((a.clone(), b.clone()));
+                            true
+                        }
+                        None => false,
+                    })
+            }
+            _ => false,
+        },
+        _ if number::is_number(&a) && number::is_number(&b) => {
+            number::compare(&a, &b) == Some(Some(Ordering::Equal))
+        }
         _ => a == b,
     }
 }
 
-fn addition_operands_error(token: Token) -> RuntimeError {
+/// Checks that `a` and `b` have the same length, and queues their elements
+/// to be compared pairwise.
+fn elements_equal(a: &[Object], b: &[Object], pending: &mut Vec<(Object, Object)>) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    pending.extend(a.iter().cloned().zip(b.iter().cloned()).rev());
+    true
+}
+
+fn slice(
+    elements: &[Object],
+    start: Option<Object>,
+    end: Option<Object>,
+    bracket: &Token,
+) -> Result<Vec<Object>, RuntimeError> {
+    let len = elements.len();
+    let start = builtins::slice_bound(start, 0, len, bracket)?;
+    let end = builtins::slice_bound(end, len, len, bracket)?;
+    Ok(elements[start..end.max(start)].to_vec())
+}
+
+/// Applies `|`, `&` or `-` to two sets, giving their union, intersection or
+/// difference.
+fn set_operation(operator: &Token, left: Object, right: Object) -> Result<Object, RuntimeError> {
+    let (Object::Set(left), Object::Set(right)) = (&left, &right) else {
+        return Err(RuntimeError::new(
+            operator.clone(),
+            "Operands must be two sets.".to_owned(),
+        ));
+    };
+    let (left, right) = (left.borrow(), right.borrow());
+    let set: IndexSet<MapKey> = match operator.token_type {
+        TokenType::Pipe => left.union(&right).cloned().collect(),
+        TokenType::Ampersand => left.intersection(&right).cloned().collect(),
+        _ => left.difference(&right).cloned().collect(),
+    };
+    Ok(Object::Set(Rc::new(RefCell::new(set))))
+}
+
+fn range(operator: &Token, start: Object, end: Object) -> Result<Object, RuntimeError> {
+    let (Object::Int(start), Object::Int(end)) = (start, end) else {
+        return Err(RuntimeError::new(
+            operator.clone(),
+            "Range bounds must be integers.".to_owned(),
+        ));
+    };
+    Ok(Object::Range(Range {
+        start,
+        end,
+        inclusive: operator.token_type == TokenType::DotDotEqual,
+    }))
+}
+
+/// Calls anything that isn't a Lox function, which only builtin functions
+/// and methods can be.
+fn call_builtin(
+    callee: Object,
+    arguments: Vec<Object>,
+    named_arguments: Vec<(Token, Object)>,
+    paren: Token,
+) -> Result<Object, RuntimeError> {
+    let arity = match &callee {
+        Object::BuiltinMethod(method) => method.arity,
+        Object::BuiltinFunction(function) => function.arity,
+        _ => {
+            return Err(RuntimeError::new(
+                paren,
+                "Can only call functions and classes.".to_owned(),
+            ))
+        }
+    };
+    if let Some((name, _)) = named_arguments.first() {
+        return Err(RuntimeError::new(
+            name.clone(),
+            format!("Unexpected argument '{}'.", name.lexeme),
+        ));
+    }
+    check_arity(arity, arguments.len(), &paren)?;
+    match callee {
+        Object::BuiltinMethod(method) => builtins::call_method(&method, arguments, &paren),
+        Object::BuiltinFunction(function) => builtins::call_function(&function, arguments, &paren),
+        _ => unreachable!(),
+    }
+}
+
+/// The name a function shows in stack traces.
+fn function_name(closure: &Closure) -> Symbol {
+    closure
+        .name
+        .unwrap_or_else(|| Symbol::intern("<anonymous>"))
+}
+
+fn check_arity(arity: usize, count: usize, paren: &Token) -> Result<(), RuntimeError> {
+    if arity == count {
+        return Ok(());
+    }
+    Err(RuntimeError::new(
+        paren.clone(),
+        format!("Expected {arity} arguments but got {count}."),
+    ))
+}
+
+fn index_operand_error(token: Token) -> RuntimeError {
     RuntimeError::new(
         token,
-        "Operands must be two numbers or two strings.".to_owned(),
+        "Only lists, tuples and maps can be indexed.".to_owned(),
     )
 }
 
-fn number_operands_error(token: Token) -> RuntimeError {
-    RuntimeError::new(token, "Operands must be numbers.".to_owned())
+pub fn undefined_key_error(token: Token, key: MapKey) -> RuntimeError {
+    RuntimeError::new(
+        token,
+        format!("Undefined key {}.", stringify_element(key.0)),
+    )
+}
+
+fn addition_operands_error(token: Token) -> RuntimeError {
+    RuntimeError::new(
+        token,
+        "Operands must be two numbers or two strings.".to_owned(),
+    )
 }
 
 fn number_operand_error(token: Token) -> RuntimeError {
     RuntimeError::new(token, "Operand must be a number.".to_owned())
 }
+
+fn integer_operand_error(token: Token) -> RuntimeError {
+    RuntimeError::new(token, "Operand must be an integer.".to_owned())
+}
+
+pub fn number_error(token: Token, error: NumberError) -> RuntimeError {
+    let message = match error {
+        NumberError::NotNumber => "Operands must be numbers.",
+        NumberError::NotInteger => "Operands must be integers.",
+        NumberError::DecimalAndFloat => "Cannot mix decimal and float operands.",
+        NumberError::DivisionByZero => "Division by zero.",
+        NumberError::ShiftAmount => "Shift amount must be between 0 and 63.",
+        NumberError::ExponentTooLarge => "Exponent is too large.",
+        NumberError::DecimalExponent => "Decimal exponent must be an integer.",
+        NumberError::Fractional => "Can't convert a number with a fractional part to an integer.",
+        NumberError::OutOfRange => "Number is out of range for the conversion.",
+    };
+    RuntimeError::new(token, message.to_owned())
+}
@@ -0,0 +1,178 @@
+use std::{cell::RefCell, rc::Rc};
+
+use indexmap::{IndexMap, IndexSet};
+
+use crate::{
+    interner::{LoxString, Symbol},
+    interpreter::RuntimeError,
+    map::MapKey,
+    scanner::{Object, Token},
+};
+
+/// Steps through the values of an iterable object. It only depends on the
+/// object model, so any backend can drive a `for` loop with it, passing in
+/// how it calls a function for iterators written in Lox.
+///
+/// Lists, maps and sets are read one position at a time rather than
+/// snapshotted, so changes made by the loop body are seen by later steps.
+///
+/// Anything else can be made iterable by following the iterator protocol:
+/// - an iterator is a function, or a map whose `"next"` entry is a function,
+///   that is called with no arguments for each value until it returns `done`
+/// - an iterable is an iterator, or a map whose `"iter"` entry is a function
+///   returning one
+pub enum ObjectIter {
+    List {
+        list: Rc<RefCell<Vec<Object>>>,
+        index: usize,
+    },
+    Tuple {
+        tuple: Rc<Vec<Object>>,
+        index: usize,
+    },
+    /// Iterating a map yields its keys.
+    Map {
+        map: Rc<RefCell<IndexMap<MapKey, Object>>>,
+        index: usize,
+    },
+    Set {
+        set: Rc<RefCell<IndexSet<MapKey>>>,
+        index: usize,
+    },
+    /// Iterating a string yields each character as a string. `index` is a
+    /// byte offset.
+    Str { string: LoxString, index: usize },
+    /// Counts from `next` up to, but not including, `end`. The bounds are
+    /// widened so that `..=` up to `i64::MAX` can't overflow.
+    Range { next: i128, end: i128 },
+    /// An iterator following the protocol, with its `next` function.
+    Protocol { next: Object },
+}
+
+impl ObjectIter {
+    /// Starts iterating `object`, reporting at `token` if it can't be
+    /// iterated. `call` calls a function with no arguments, for an `iter`
+    /// function.
+    pub fn new(
+        object: Object,
+        token: &Token,
+        call: impl FnOnce(Object) -> Result<Object, RuntimeError>,
+    ) -> Result<ObjectIter, RuntimeError> {
+        match object {
+            Object::List(list) => Ok(ObjectIter::List { list, index: 0 }),
+            Object::Tuple(tuple) => Ok(ObjectIter::Tuple { tuple, index: 0 }),
+            Object::Map(map) => {
+                if let Some(iter) = protocol_function(&map, "iter") {
+                    return match iterator_next(call(iter)?) {
+                        Some(next) => Ok(ObjectIter::Protocol { next }),
+                        None => Err(RuntimeError::new(
+                            token.clone(),
+                            "'iter' must return a function or a map with a 'next' function."
+                                .to_owned(),
+                        )),
+                    };
+                }
+                match protocol_function(&map, "next") {
+                    Some(next) => Ok(ObjectIter::Protocol { next }),
+                    None => Ok(ObjectIter::Map { map, index: 0 }),
+                }
+            }
+            Object::Set(set) => Ok(ObjectIter::Set { set, index: 0 }),
+            Object::Str(string) => Ok(ObjectIter::Str { string, index: 0 }),
+            Object::Range(range) => Ok(ObjectIter::Range {
+                next: range.start as i128,
+                end: range.end as i128 + range.inclusive as i128,
+            }),
+            object if is_callable(&object) => Ok(ObjectIter::Protocol { next: object }),
+            _ => Err(RuntimeError::new(
+                token.clone(),
+                "Can only iterate over lists, tuples, maps, sets, strings, ranges and iterators."
+                    .to_owned(),
+            )),
+        }
+    }
+
+    /// The next value, or `None` once there are no more. `call` calls a
+    /// function with no arguments, for a `next` function.
+    pub fn next(
+        &mut self,
+        call: impl FnOnce(Object) -> Result<Object, RuntimeError>,
+    ) -> Result<Option<Object>, RuntimeError> {
+        match self {
+            ObjectIter::Protocol { next } => match call(next.clone())? {
+                Object::Done => Ok(None),
+                value => Ok(Some(value)),
+            },
+            builtin => Ok(builtin.next_builtin()),
+        }
+    }
+
+    fn next_builtin(&mut self) -> Option<Object> {
+        match self {
+            ObjectIter::List { list, index } => {
+                let value = list.borrow().get(*index).cloned();
+                *index += 1;
+                value
+            }
+            ObjectIter::Tuple { tuple, index } => {
+                let value = tuple.get(*index).cloned();
+                *index += 1;
+                value
+            }
+            ObjectIter::Map { map, index } => {
+                let key = map.borrow().get_index(*index).map(|(key, _)| key.0.clone());
+                *index += 1;
+                key
+            }
+            ObjectIter::Set { set, index } => {
+                let element = set
+                    .borrow()
+                    .get_index(*index)
+                    .map(|element| element.0.clone());
+                *index += 1;
+                element
+            }
+            ObjectIter::Str { string, index } => {
+                let c = string[*index..].chars().next()?;
+                *index += c.len_utf8();
+                Some(Object::Str(LoxString::from(
+                    c.encode_utf8(&mut [0; 4]) as &str
+                )))
+            }
+            ObjectIter::Range { next, end } => {
+                if *next >= *end {
+                    return None;
+                }
+                let value = Object::Int(*next as i64);
+                *next += 1;
+                Some(value)
+            }
+            ObjectIter::Protocol { .. } => unreachable!("protocol iterators call `next`"),
+        }
+    }
+}
+
+fn is_callable(object: &Object) -> bool {
+    matches!(
+        object,
+        Object::Closure(_) | Object::BuiltinFunction(_) | Object::BuiltinMethod(_)
+    )
+}
+
+/// The function in the `name` entry of `map`, if there is one.
+fn protocol_function(map: &RefCell<IndexMap<MapKey, Object>>, name: &str) -> Option<Object> {
+    let key = MapKey(Object::Str(LoxString::Interned(Symbol::intern(name))));
+    map.borrow()
+        .get(&key)
+        .filter(|value| is_callable(value))
+        .cloned()
+}
+
+/// The `next` function of an iterator returned by an `iter` function.
+fn iterator_next(iterator: Object) -> Option<Object> {
+    match iterator {
+        Object::Map(map) => protocol_function(&map, "next"),
+        object if is_callable(&object) => Some(object),
+        _ => None,
+    }
+}
@@ -3,22 +3,57 @@ use std::fs;
 use std::io;
 use std::io::Write;
 use std::process::exit;
+use std::thread;
 
-use interpreter::interpret;
+use interpreter::Interpreter;
 use interpreter::RuntimeError;
+use optimizer::optimize;
 use parser::Parser;
 use scanner::Scanner;
 use scanner::Token;
 use scanner::TokenType;
 
 mod ast;
+mod builtins;
+mod environment;
+mod function;
+mod interner;
 mod interpreter;
+mod iterator;
+mod map;
+mod number;
+mod optimizer;
 mod parser;
 mod scanner;
 
+/// How deeply statements and expressions can be run inside each other by
+/// default, counting those in the functions being called.
+const MAX_DEPTH: usize = 10000;
+/// How deeply statements and expressions can be nested by default. A chain
+/// of operators like `a + b + c` builds a tree as deep as it is long, so this
+/// is set well beyond the longest chains found in real and generated code.
+const MAX_NESTING: usize = 5000;
+/// A generous bound on the native stack one level of depth or nesting takes.
+/// The interpreter and parser recurse natively, and the stack of the thread
+/// running Lox code is sized from the limits with this.
+const FRAME_SIZE: usize = if cfg!(debug_assertions) {
+    48 * 1024
+} else {
+    4 * 1024
+};
+/// Stack for everything around the recursion, such as the REPL loop.
+const STACK_BASE: usize = 1024 * 1024;
+/// The largest stack the thread running Lox code gets. Higher limits are
+/// lowered to fit, since overflowing it would abort the process.
+const MAX_STACK_SIZE: usize = 1024 * 1024 * 1024;
+
 pub struct Lox {
     had_error: bool,
     had_runtime_error: bool,
+    fold_constants: bool,
+    max_depth: usize,
+    max_nesting: usize,
+    interpreter: Interpreter,
 }
 
 impl Lox {
@@ -26,10 +61,15 @@ impl Lox {
         Lox {
             had_error: false,
             had_runtime_error: false,
+            fold_constants: true,
+            max_depth: MAX_DEPTH,
+            max_nesting: MAX_NESTING,
+            interpreter: Interpreter::new("<stdin>", MAX_DEPTH),
         }
     }
 
     fn run_prompt(&mut self) {
+        self.interpreter = Interpreter::new("<stdin>", self.max_depth);
         loop {
             print!("> ");
             let _ = io::stdout().flush();
@@ -39,7 +79,8 @@ impl Lox {
                     if n == 0 {
                         break;
                     }
-                    self.run(&input)
+                    self.run(&input);
+                    self.had_error = false;
                 }
                 Err(error) => println!("error: {error}"),
             }
@@ -49,6 +90,7 @@ impl Lox {
     fn run_file(&mut self, file_path: &String) {
         match fs::read_to_string(file_path) {
             Ok(source) => {
+                self.interpreter = Interpreter::new(file_path, self.max_depth);
                 self.run(&source);
                 if self.had_error {
                     exit(65)
@@ -67,9 +109,19 @@ impl Lox {
         let mut parser = Parser::new(&tokens, self);
         let statements = parser.parse();
 
-        match statements {
-            Ok(stmts) => interpret(stmts, self),
-            Err(_) => (),
+        // scan errors don't stop the parser, so check for them here too
+        if self.had_error {
+            return;
+        }
+        if let Ok(stmts) = statements {
+            let stmts = if self.fold_constants {
+                optimize(stmts)
+            } else {
+                stmts
+            };
+            if let Err(error) = self.interpreter.interpret(stmts) {
+                self.runtime_error(error);
+            }
         }
     }
 
@@ -82,7 +134,19 @@ impl Lox {
     }
 
     fn runtime_error(&mut self, error: RuntimeError) {
-        eprintln!("{}\n[line {}]", error.message, error.token.line);
+        eprintln!("{}", error.message);
+        // runaway recursion repeats the same frame many times over
+        let mut frames = error.stack.iter().peekable();
+        while let Some(frame) = frames.next() {
+            eprintln!("{frame}");
+            let mut repeats = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeats += 1;
+            }
+            if repeats > 0 {
+                eprintln!("[previous frame repeated {repeats} more times]");
+            }
+        }
         self.had_runtime_error = true;
     }
 
@@ -92,13 +156,101 @@ impl Lox {
     }
 }
 
+/// The command line settings. They're read before Lox code runs, since the
+/// stack it runs on is sized from the limits.
+#[derive(Clone)]
+struct Options {
+    fold_constants: bool,
+    max_depth: usize,
+    max_nesting: usize,
+    file_path: Option<String>,
+}
+
+impl Options {
+    fn parse() -> Options {
+        let mut options = Options {
+            fold_constants: true,
+            max_depth: MAX_DEPTH,
+            max_nesting: MAX_NESTING,
+            file_path: None,
+        };
+        for arg in env::args().skip(1) {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, value.parse().ok()),
+                None => (arg.as_str(), None),
+            };
+            match (flag, value) {
+                ("--no-fold", None) => options.fold_constants = false,
+                ("--max-depth", Some(max_depth)) => options.max_depth = max_depth,
+                ("--max-nesting", Some(max_nesting)) => options.max_nesting = max_nesting,
+                _ if options.file_path.is_none() && !arg.starts_with("--") => {
+                    options.file_path = Some(arg)
+                }
+                _ => {
+                    eprintln!("Usage: rlox [--no-fold] [--max-depth=N] [--max-nesting=N] [script]");
+                    exit(64)
+                }
+            }
+        }
+
+        let levels = (MAX_STACK_SIZE - STACK_BASE) / FRAME_SIZE;
+        options.max_nesting = options.max_nesting.min(levels / 2);
+        options.max_depth = options.max_depth.min(levels - options.max_nesting);
+        options
+    }
+
+    /// The stack that running code within the limits can take.
+    fn stack_size(&self) -> usize {
+        STACK_BASE + (self.max_depth + self.max_nesting) * FRAME_SIZE
+    }
+}
+
+/// The lowest limits worth falling back to when the stack for higher ones
+/// can't be had.
+const MIN_LIMIT: usize = 64;
+
 fn main() {
+    let mut options = Options::parse();
+    let requested = (options.max_depth, options.max_nesting);
+    // the stack is only reserved up front, but the system can still refuse
+    // to reserve that much, as it does under `ulimit -v`
+    loop {
+        let thread_options = options.clone();
+        let spawned = thread::Builder::new()
+            .stack_size(options.stack_size())
+            .spawn(move || run_lox(thread_options, requested));
+        match spawned {
+            Ok(lox_thread) => {
+                if lox_thread.join().is_err() {
+                    exit(101)
+                }
+                return;
+            }
+            Err(_) if options.max_depth.max(options.max_nesting) > MIN_LIMIT => {
+                options.max_depth = (options.max_depth / 2).max(MIN_LIMIT.min(requested.0));
+                options.max_nesting = (options.max_nesting / 2).max(MIN_LIMIT.min(requested.1));
+            }
+            Err(error) => {
+                eprintln!("Could not reserve a stack to run Lox code on: {error}");
+                exit(64)
+            }
+        }
+    }
+}
+
+fn run_lox(options: Options, requested: (usize, usize)) {
+    if (options.max_depth, options.max_nesting) != requested {
+        eprintln!(
+            "Lowered the limits to --max-depth={} --max-nesting={} to fit the stack available.",
+            options.max_depth, options.max_nesting
+        );
+    }
     let mut lox = Lox::new();
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 {
-        lox.run_prompt();
-    } else {
-        let file_path = &args[1];
-        lox.run_file(file_path);
+    lox.fold_constants = options.fold_constants;
+    lox.max_depth = options.max_depth;
+    lox.max_nesting = options.max_nesting;
+    match options.file_path {
+        Some(file_path) => lox.run_file(&file_path),
+        None => lox.run_prompt(),
     }
 }
@@ -0,0 +1,77 @@
+use std::hash::{Hash, Hasher};
+
+use crate::{
+    interpreter::{is_equal, RuntimeError},
+    number,
+    scanner::{Object, Token},
+};
+
+/// A value used as a map key. Hashing and equality agree with `==`, so `1`,
+/// `1.0` and `1.0d` all find the same entry.
+#[derive(Debug, Clone)]
+pub struct MapKey(pub Object);
+
+impl MapKey {
+    /// Wraps `object` as a map key or set element. Lists, maps and sets are
+    /// rejected because mutating them would change their hash while they sit
+    /// in the collection.
+    pub fn new(object: Object, token: &Token) -> Result<MapKey, RuntimeError> {
+        // the elements of tuples, however deeply nested, are checked from a
+        // stack rather than recursively
+        let mut pending = vec![&object];
+        while let Some(element) = pending.pop() {
+            let kind = match element {
+                Object::List(_) => "a list",
+                Object::Map(_) => "a map",
+                Object::Set(_) => "a set",
+                Object::BuiltinMethod(_) => "a method",
+                Object::Tuple(elements) => {
+                    pending.extend(elements.iter());
+                    continue;
+                }
+                _ => continue,
+            };
+            return Err(RuntimeError::new(
+                token.clone(),
+                format!("Can't hash {kind}."),
+            ));
+        }
+        Ok(MapKey(object))
+    }
+}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // a tuple hashes as its elements, which are taken from a stack rather
+        // than hashed recursively since tuples can nest deeper than the
+        // native stack allows
+        let mut pending = vec![&self.0];
+        while let Some(object) = pending.pop() {
+            match object {
+                Object::Str(value) => value.hash(state),
+                Object::Bool(value) => value.hash(state),
+                Object::Nil => {}
+                Object::Tuple(elements) => pending.extend(elements.iter().rev()),
+                // equal ranges contain the same integers, which empty ranges
+                // trivially do whatever their bounds
+                Object::Range(range) => {
+                    range.len().hash(state);
+                    if range.len() > 0 {
+                        range.start.hash(state);
+                    }
+                }
+                object => number::hash(object, state),
+            }
+        }
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &MapKey) -> bool {
+        is_equal(self.0.clone(), other.0.clone())
+    }
+}
+
+// NaN is not equal to itself, so a NaN key can be inserted but never found
+// again, as in most languages.
+impl Eq for MapKey {}
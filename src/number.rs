@@ -0,0 +1,520 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Pow, Signed, ToPrimitive, Zero};
+
+use crate::scanner::Object;
+
+/// Why an arithmetic or bitwise operation could not produce a value.
+pub enum NumberError {
+    /// An operand was not a number.
+    NotNumber,
+    /// An operand of a bitwise operator was not an integer.
+    NotInteger,
+    /// A decimal was combined with a float, which would silently lose the
+    /// decimal's exactness.
+    DecimalAndFloat,
+    DivisionByZero,
+    ShiftAmount,
+    ExponentTooLarge,
+    DecimalExponent,
+    /// A conversion to an integer would drop a fractional part.
+    Fractional,
+    /// A conversion's result can't represent the value, like an infinite
+    /// float as a decimal.
+    OutOfRange,
+}
+
+/// The operands of an arithmetic operator, promoted to a common type.
+///
+/// Integers and bigints promote to bigints, any integer promotes to a
+/// decimal, and any integer promotes to a float. Decimals and floats never
+/// mix.
+enum Operands {
+    Int(i64, i64),
+    BigInt(BigInt, BigInt),
+    Decimal(BigDecimal, BigDecimal),
+    F64(f64, f64),
+}
+
+fn promote(left: Object, right: Object) -> Result<Operands, NumberError> {
+    match (left, right) {
+        (Object::Int(left), Object::Int(right)) => Ok(Operands::Int(left, right)),
+        (Object::F64(left), Object::F64(right)) => Ok(Operands::F64(left, right)),
+        (Object::Decimal(left), Object::Decimal(right)) => Ok(Operands::Decimal(left, right)),
+        (Object::Decimal(_), Object::F64(_)) | (Object::F64(_), Object::Decimal(_)) => {
+            Err(NumberError::DecimalAndFloat)
+        }
+        (Object::F64(left), right) => Ok(Operands::F64(left, integer_to_f64(&right)?)),
+        (left, Object::F64(right)) => Ok(Operands::F64(integer_to_f64(&left)?, right)),
+        (Object::Decimal(left), right) => {
+            Ok(Operands::Decimal(left, BigDecimal::from(to_bigint(right)?)))
+        }
+        (left, Object::Decimal(right)) => {
+            Ok(Operands::Decimal(BigDecimal::from(to_bigint(left)?), right))
+        }
+        (left, right) => Ok(Operands::BigInt(to_bigint(left)?, to_bigint(right)?)),
+    }
+}
+
+fn integer_to_f64(object: &Object) -> Result<f64, NumberError> {
+    match object {
+        Object::Int(value) => Ok(*value as f64),
+        Object::BigInt(value) => Ok(value.to_f64().unwrap_or(f64::NAN)),
+        _ => Err(NumberError::NotNumber),
+    }
+}
+
+fn to_bigint(object: Object) -> Result<BigInt, NumberError> {
+    match object {
+        Object::Int(value) => Ok(BigInt::from(value)),
+        Object::BigInt(value) => Ok(value),
+        _ => Err(NumberError::NotNumber),
+    }
+}
+
+/// Wraps a bigint result, narrowing it back to an `Int` when it fits so that
+/// every integer has exactly one representation.
+pub fn from_bigint(value: BigInt) -> Object {
+    match value.to_i64() {
+        Some(value) => Object::Int(value),
+        None => Object::BigInt(value),
+    }
+}
+
+pub fn add(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match promote(left, right)? {
+        Operands::Int(left, right) => match left.checked_add(right) {
+            Some(value) => Object::Int(value),
+            None => from_bigint(BigInt::from(left) + right),
+        },
+        Operands::BigInt(left, right) => from_bigint(left + right),
+        Operands::Decimal(left, right) => Object::Decimal(left + right),
+        Operands::F64(left, right) => Object::F64(left + right),
+    })
+}
+
+pub fn subtract(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match promote(left, right)? {
+        Operands::Int(left, right) => match left.checked_sub(right) {
+            Some(value) => Object::Int(value),
+            None => from_bigint(BigInt::from(left) - right),
+        },
+        Operands::BigInt(left, right) => from_bigint(left - right),
+        Operands::Decimal(left, right) => Object::Decimal(left - right),
+        Operands::F64(left, right) => Object::F64(left - right),
+    })
+}
+
+pub fn multiply(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match promote(left, right)? {
+        Operands::Int(left, right) => match left.checked_mul(right) {
+            Some(value) => Object::Int(value),
+            None => from_bigint(BigInt::from(left) * right),
+        },
+        Operands::BigInt(left, right) => from_bigint(left * right),
+        Operands::Decimal(left, right) => Object::Decimal(left * right),
+        Operands::F64(left, right) => Object::F64(left * right),
+    })
+}
+
+/// True division. Integers divide as floats; decimals stay exact up to the
+/// default decimal precision.
+pub fn divide(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match promote(left, right)? {
+        Operands::Int(left, right) => Object::F64(left as f64 / right as f64),
+        Operands::BigInt(left, right) => {
+            Object::F64(left.to_f64().unwrap_or(f64::NAN) / right.to_f64().unwrap_or(f64::NAN))
+        }
+        Operands::Decimal(_, right) if right.is_zero() => return Err(NumberError::DivisionByZero),
+        Operands::Decimal(left, right) => Object::Decimal(left / right),
+        Operands::F64(left, right) => Object::F64(left / right),
+    })
+}
+
+/// Floored remainder: the result has the sign of the divisor, so that
+/// `a == b * (a ~/ b) + a % b` always holds.
+pub fn modulo(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match promote(left, right)? {
+        Operands::Int(_, 0) => return Err(NumberError::DivisionByZero),
+        Operands::Int(left, right) => Object::Int(int_modulo(left, right)),
+        Operands::BigInt(_, right) if right.is_zero() => return Err(NumberError::DivisionByZero),
+        Operands::BigInt(left, right) => from_bigint(bigint_modulo(left, right)),
+        Operands::Decimal(_, right) if right.is_zero() => return Err(NumberError::DivisionByZero),
+        Operands::Decimal(left, right) => Object::Decimal(decimal_modulo(left, right)),
+        Operands::F64(left, right) => Object::F64(f64_modulo(left, right)),
+    })
+}
+
+/// Division rounding towards negative infinity.
+pub fn floor_divide(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match promote(left, right)? {
+        Operands::Int(_, 0) => return Err(NumberError::DivisionByZero),
+        Operands::Int(left, right) => match int_floor_div(left, right) {
+            Some(value) => Object::Int(value),
+            None => from_bigint(bigint_floor_div(BigInt::from(left), BigInt::from(right))),
+        },
+        Operands::BigInt(_, right) if right.is_zero() => return Err(NumberError::DivisionByZero),
+        Operands::BigInt(left, right) => from_bigint(bigint_floor_div(left, right)),
+        Operands::Decimal(_, right) if right.is_zero() => return Err(NumberError::DivisionByZero),
+        Operands::Decimal(left, right) => {
+            // subtracting the remainder leaves an exact multiple of `right`,
+            // so this division never has to round
+            let remainder = decimal_modulo(left.clone(), right.clone());
+            Object::Decimal(((left - remainder) / right).with_scale(0))
+        }
+        Operands::F64(left, right) => Object::F64((left / right).floor()),
+    })
+}
+
+pub fn power(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match promote(left, right)? {
+        Operands::Int(left, right) if right >= 0 => {
+            let base_bits = u64::from(64 - left.unsigned_abs().leading_zeros());
+            let exponent = exact_exponent(base_bits, u32::try_from(right).ok())?;
+            match left.checked_pow(exponent) {
+                Some(value) => Object::Int(value),
+                None => from_bigint(BigInt::from(left).pow(exponent)),
+            }
+        }
+        Operands::Int(left, right) => Object::F64((left as f64).powf(right as f64)),
+        Operands::BigInt(left, right) if right.is_negative() => Object::F64(
+            left.to_f64()
+                .unwrap_or(f64::NAN)
+                .powf(right.to_f64().unwrap_or(f64::NAN)),
+        ),
+        Operands::BigInt(left, right) => {
+            let exponent = exact_exponent(left.bits(), right.to_u32())?;
+            from_bigint(left.pow(exponent))
+        }
+        Operands::Decimal(left, right) => {
+            if !right.is_integer() {
+                return Err(NumberError::DecimalExponent);
+            }
+            let exponent = right.with_scale(0).into_bigint_and_exponent().0;
+            // trailing zeros in the digits don't make the result any bigger
+            let digits = left.normalized().into_bigint_and_exponent().0;
+            let magnitude = exact_exponent(digits.bits(), exponent.abs().to_u32())?;
+            let value = decimal_pow(left, magnitude);
+            if !exponent.is_negative() {
+                Object::Decimal(value)
+            } else if value.is_zero() {
+                return Err(NumberError::DivisionByZero);
+            } else {
+                Object::Decimal(BigDecimal::from(1) / value)
+            }
+        }
+        Operands::F64(left, right) => Object::F64(left.powf(right)),
+    })
+}
+
+/// The most bits of digits an exact power may have. Computing bigger ones
+/// would take time and memory out of all proportion to the expression.
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+/// Checks the exponent of an exact power whose base has `base_bits` bits of
+/// digits, failing if it doesn't fit in a `u32` or the result would be too
+/// large. Powers of 0, 1 and -1 never grow, so any exponent works for them.
+fn exact_exponent(base_bits: u64, exponent: Option<u32>) -> Result<u32, NumberError> {
+    let exponent = exponent.ok_or(NumberError::ExponentTooLarge)?;
+    if base_bits.saturating_sub(1) * u64::from(exponent) > MAX_POWER_BITS {
+        return Err(NumberError::ExponentTooLarge);
+    }
+    Ok(exponent)
+}
+
+/// Converts a number to an integer. Unlike the implicit promotions this never
+/// rounds: a number with a fractional part is an error.
+pub fn to_int(object: Object) -> Result<Object, NumberError> {
+    match object {
+        Object::Int(_) | Object::BigInt(_) => Ok(object),
+        Object::F64(value) if !value.is_finite() => Err(NumberError::OutOfRange),
+        Object::F64(value) if value.fract() != 0.0 => Err(NumberError::Fractional),
+        Object::F64(value) => Ok(from_bigint(
+            BigInt::from_f64(value).ok_or(NumberError::OutOfRange)?,
+        )),
+        Object::Decimal(value) => {
+            let (digits, scale) = value.normalized().into_bigint_and_exponent();
+            if digits.is_zero() {
+                return Ok(Object::Int(0));
+            }
+            if scale > 0 {
+                return Err(NumberError::Fractional);
+            }
+            // a huge exponent would need as many digits written out; a
+            // decimal digit takes a little over 3 bits
+            let zeros = scale.unsigned_abs();
+            if digits.bits().saturating_add(zeros.saturating_mul(4)) > MAX_POWER_BITS {
+                return Err(NumberError::OutOfRange);
+            }
+            Ok(from_bigint(digits * BigInt::from(10).pow(zeros as u32)))
+        }
+        _ => Err(NumberError::NotNumber),
+    }
+}
+
+/// Converts a number to an exact decimal. A float becomes the shortest
+/// decimal that reads back as the same float, so `decimal(0.1)` is `0.1d`
+/// rather than the binary fraction the float actually holds.
+pub fn to_decimal(object: Object) -> Result<Object, NumberError> {
+    match object {
+        Object::Int(value) => Ok(Object::Decimal(BigDecimal::from(value))),
+        Object::BigInt(value) => Ok(Object::Decimal(BigDecimal::from(value))),
+        Object::Decimal(_) => Ok(object),
+        Object::F64(value) if !value.is_finite() => Err(NumberError::OutOfRange),
+        Object::F64(value) => value
+            .to_string()
+            .parse()
+            .map(Object::Decimal)
+            .map_err(|_| NumberError::OutOfRange),
+        _ => Err(NumberError::NotNumber),
+    }
+}
+
+/// Converts a number to the nearest float. Values too large for a float are
+/// an error rather than becoming infinite.
+pub fn to_float(object: Object) -> Result<Object, NumberError> {
+    let value = match object {
+        Object::Int(value) => value as f64,
+        Object::BigInt(value) => value.to_f64().unwrap_or(f64::INFINITY),
+        Object::Decimal(value) => value.to_f64().unwrap_or(f64::INFINITY),
+        Object::F64(value) => return Ok(Object::F64(value)),
+        _ => return Err(NumberError::NotNumber),
+    };
+    if value.is_finite() {
+        Ok(Object::F64(value))
+    } else {
+        Err(NumberError::OutOfRange)
+    }
+}
+
+pub fn negate(right: Object) -> Result<Object, NumberError> {
+    match right {
+        Object::Int(value) => Ok(match value.checked_neg() {
+            Some(value) => Object::Int(value),
+            None => from_bigint(-BigInt::from(value)),
+        }),
+        Object::BigInt(value) => Ok(from_bigint(-value)),
+        Object::Decimal(value) => Ok(Object::Decimal(-value)),
+        Object::F64(value) => Ok(Object::F64(-value)),
+        _ => Err(NumberError::NotNumber),
+    }
+}
+
+/// The operands of a bitwise operator.
+enum IntegerOperands {
+    Int(i64, i64),
+    BigInt(BigInt, BigInt),
+}
+
+/// The value of `object` as an integer, if it is an integer or a float with
+/// no fractional part that fits in 64 bits.
+fn as_integer(object: Object) -> Result<Object, NumberError> {
+    match object {
+        Object::Int(_) | Object::BigInt(_) => Ok(object),
+        Object::F64(value)
+            if value.fract() == 0.0 && value >= i64::MIN as f64 && value < i64::MAX as f64 =>
+        {
+            Ok(Object::Int(value as i64))
+        }
+        _ => Err(NumberError::NotInteger),
+    }
+}
+
+fn integer_operands(left: Object, right: Object) -> Result<IntegerOperands, NumberError> {
+    match (as_integer(left)?, as_integer(right)?) {
+        (Object::Int(left), Object::Int(right)) => Ok(IntegerOperands::Int(left, right)),
+        (left, right) => Ok(IntegerOperands::BigInt(to_bigint(left)?, to_bigint(right)?)),
+    }
+}
+
+pub fn bit_and(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match integer_operands(left, right)? {
+        IntegerOperands::Int(left, right) => Object::Int(left & right),
+        IntegerOperands::BigInt(left, right) => from_bigint(left & right),
+    })
+}
+
+pub fn bit_or(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match integer_operands(left, right)? {
+        IntegerOperands::Int(left, right) => Object::Int(left | right),
+        IntegerOperands::BigInt(left, right) => from_bigint(left | right),
+    })
+}
+
+pub fn bit_xor(left: Object, right: Object) -> Result<Object, NumberError> {
+    Ok(match integer_operands(left, right)? {
+        IntegerOperands::Int(left, right) => Object::Int(left ^ right),
+        IntegerOperands::BigInt(left, right) => from_bigint(left ^ right),
+    })
+}
+
+pub fn bit_not(right: Object) -> Result<Object, NumberError> {
+    Ok(match as_integer(right)? {
+        Object::Int(value) => Object::Int(!value),
+        value => from_bigint(!to_bigint(value)?),
+    })
+}
+
+/// Shifting left multiplies by a power of two, promoting to a bigint rather
+/// than dropping bits off the top.
+pub fn shift_left(left: Object, right: Object) -> Result<Object, NumberError> {
+    let (left, amount) = shift_operands(left, right)?;
+    Ok(from_bigint(left << amount))
+}
+
+pub fn shift_right(left: Object, right: Object) -> Result<Object, NumberError> {
+    let (left, amount) = shift_operands(left, right)?;
+    Ok(from_bigint(left >> amount))
+}
+
+fn shift_operands(left: Object, right: Object) -> Result<(BigInt, u32), NumberError> {
+    let (left, amount) = match integer_operands(left, right)? {
+        IntegerOperands::Int(left, right) => (BigInt::from(left), right.to_u32()),
+        IntegerOperands::BigInt(left, right) => (left, right.to_u32()),
+    };
+    match amount {
+        Some(amount) if amount < 64 => Ok((left, amount)),
+        _ => Err(NumberError::ShiftAmount),
+    }
+}
+
+/// A number converted to an exact decimal so that values of different types
+/// can be compared without rounding. NaN and the infinities have no decimal
+/// form and are kept as floats.
+enum Exact {
+    Finite(BigDecimal),
+    Float(f64),
+}
+
+fn exact(object: &Object) -> Option<Exact> {
+    match object {
+        Object::Int(value) => Some(Exact::Finite(BigDecimal::from(*value))),
+        Object::BigInt(value) => Some(Exact::Finite(BigDecimal::from(value.clone()))),
+        Object::Decimal(value) => Some(Exact::Finite(value.clone())),
+        Object::F64(value) => match BigDecimal::try_from(*value) {
+            Ok(value) => Some(Exact::Finite(value)),
+            Err(_) => Some(Exact::Float(*value)),
+        },
+        _ => None,
+    }
+}
+
+/// Orders two numbers exactly, whatever their types. Returns `None` if either
+/// operand is not a number, and `Some(None)` if they are unordered because
+/// one of them is NaN.
+pub fn compare(left: &Object, right: &Object) -> Option<Option<Ordering>> {
+    match (left, right) {
+        (Object::Int(left), Object::Int(right)) => Some(Some(left.cmp(right))),
+        (Object::F64(left), Object::F64(right)) => Some(left.partial_cmp(right)),
+        _ => Some(match (exact(left)?, exact(right)?) {
+            (Exact::Finite(left), Exact::Finite(right)) => Some(left.cmp(&right)),
+            (Exact::Float(left), Exact::Float(right)) => left.partial_cmp(&right),
+            // a non-finite float is NaN, which is unordered, or an infinity,
+            // which is beyond every finite value
+            (Exact::Float(left), Exact::Finite(_)) => left.partial_cmp(&0.0),
+            (Exact::Finite(_), Exact::Float(right)) => 0.0.partial_cmp(&right),
+        }),
+    }
+}
+
+/// Hashes a number so that numbers of different types that compare equal,
+/// like `1`, `1.0` and `1.00d`, hash the same.
+pub fn hash<H: Hasher>(object: &Object, state: &mut H) {
+    match exact(object) {
+        Some(Exact::Finite(value)) => {
+            // normalizing drops trailing zeros, so equal decimals hash equal
+            let (digits, scale) = value.normalized().into_bigint_and_exponent();
+            digits.hash(state);
+            if !digits.is_zero() {
+                scale.hash(state);
+            }
+        }
+        Some(Exact::Float(value)) => value.to_bits().hash(state),
+        None => {}
+    }
+}
+
+pub fn is_number(object: &Object) -> bool {
+    matches!(
+        object,
+        Object::Int(_) | Object::BigInt(_) | Object::Decimal(_) | Object::F64(_)
+    )
+}
+
+fn int_modulo(left: i64, right: i64) -> i64 {
+    // wrapping only matters for i64::MIN % -1, whose remainder is 0 anyway
+    let remainder = left.wrapping_rem(right);
+    if remainder != 0 && (remainder < 0) != (right < 0) {
+        remainder + right
+    } else {
+        remainder
+    }
+}
+
+fn bigint_modulo(left: BigInt, right: BigInt) -> BigInt {
+    let remainder = left % &right;
+    if !remainder.is_zero() && remainder.is_negative() != right.is_negative() {
+        remainder + right
+    } else {
+        remainder
+    }
+}
+
+fn decimal_modulo(left: BigDecimal, right: BigDecimal) -> BigDecimal {
+    let remainder = left % right.clone();
+    if !remainder.is_zero() && remainder.is_negative() != right.is_negative() {
+        remainder + right
+    } else {
+        remainder
+    }
+}
+
+fn f64_modulo(left: f64, right: f64) -> f64 {
+    let remainder = left % right;
+    if remainder != 0.0 && (remainder < 0.0) != (right < 0.0) {
+        remainder + right
+    } else {
+        remainder
+    }
+}
+
+/// Returns `None` on overflow, which only happens for `i64::MIN ~/ -1`.
+fn int_floor_div(left: i64, right: i64) -> Option<i64> {
+    let quotient = left.checked_div(right)?;
+    if left % right != 0 && (left < 0) != (right < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
+
+fn bigint_floor_div(left: BigInt, right: BigInt) -> BigInt {
+    let remainder = &left % &right;
+    let quotient = left / &right;
+    if !remainder.is_zero() && remainder.is_negative() != right.is_negative() {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+/// Exact exponentiation by squaring. `BigDecimal::powi` rounds to the
+/// default precision, which would defeat the point of using decimals.
+fn decimal_pow(mut base: BigDecimal, mut exponent: u32) -> BigDecimal {
+    let mut result = BigDecimal::from(1);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = &result * &base;
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = base.square();
+        }
+    }
+    result
+}
@@ -0,0 +1,224 @@
+use std::rc::Rc;
+
+use crate::{
+    ast::{
+        Assign, Binary, Block, Call, Catch, Conditional, Expr, Expression, ForIn, Function, Get,
+        If, Index, Interpolation, List, Literal, Map, Param, Print, Return, Set, SetIndex, Slice,
+        Stmt, Throw, Try, Tuple, Unary, Var, While,
+    },
+    interpreter::{is_truthy, Interpreter},
+};
+
+/// Folds every literal-only subexpression in `statements` into a single
+/// `Literal` and drops the `Grouping` nodes that only mattered while parsing.
+pub fn optimize(statements: Vec<Stmt>) -> Vec<Stmt> {
+    statements.into_iter().map(fold_stmt).collect()
+}
+
+fn fold_stmt(stmt: Stmt) -> Stmt {
+    match stmt {
+        Stmt::Block(s) => Stmt::Block(Block {
+            statements: optimize(s.statements),
+        }),
+        Stmt::Break | Stmt::Continue => stmt,
+        Stmt::Expression(s) => Stmt::Expression(Expression {
+            expression: fold_expr(s.expression),
+        }),
+        Stmt::ForIn(s) => Stmt::ForIn(ForIn {
+            name: s.name,
+            keyword: s.keyword,
+            iterable: fold_expr(s.iterable),
+            body: Box::new(fold_stmt(*s.body)),
+        }),
+        Stmt::If(s) => Stmt::If(If {
+            condition: fold_expr(s.condition),
+            then_branch: Box::new(fold_stmt(*s.then_branch)),
+            else_branch: s.else_branch.map(|branch| Box::new(fold_stmt(*branch))),
+        }),
+        Stmt::Print(s) => Stmt::Print(Print {
+            expression: fold_expr(s.expression),
+        }),
+        Stmt::Return(s) => Stmt::Return(Return {
+            keyword: s.keyword,
+            value: s.value.map(fold_expr),
+        }),
+        Stmt::Throw(s) => Stmt::Throw(Throw {
+            keyword: s.keyword,
+            value: fold_expr(s.value),
+        }),
+        Stmt::Try(s) => Stmt::Try(Try {
+            body: optimize(s.body),
+            catch: s.catch.map(|catch| Catch {
+                name: catch.name,
+                body: optimize(catch.body),
+            }),
+            finally: s.finally.map(optimize),
+        }),
+        Stmt::Var(s) => Stmt::Var(Var {
+            name: s.name,
+            initializer: s.initializer.map(fold_expr),
+            constant: s.constant,
+            docs: s.docs,
+        }),
+        Stmt::While(s) => Stmt::While(While {
+            condition: fold_expr(s.condition),
+            body: Box::new(fold_stmt(*s.body)),
+        }),
+    }
+}
+
+pub fn fold_expr(expr: Expr) -> Expr {
+    match expr {
+        Expr::Assign(e) => Expr::Assign(Assign {
+            name: e.name,
+            value: Box::new(fold_expr(*e.value)),
+        }),
+        Expr::Binary(e) => {
+            let left = fold_expr(*e.left);
+            let right = fold_expr(*e.right);
+            let folded = is_literal(&left) && is_literal(&right);
+            let expr = Expr::Binary(Binary {
+                left: Box::new(left),
+                operator: e.operator,
+                right: Box::new(right),
+            });
+            if folded {
+                fold_constant(expr)
+            } else {
+                expr
+            }
+        }
+        Expr::Call(e) => Expr::Call(Call {
+            callee: Box::new(fold_expr(*e.callee)),
+            paren: e.paren,
+            arguments: e.arguments.into_iter().map(fold_expr).collect(),
+            named_arguments: e
+                .named_arguments
+                .into_iter()
+                .map(|(name, argument)| (name, fold_expr(argument)))
+                .collect(),
+        }),
+        Expr::Conditional(e) => match fold_expr(*e.condition) {
+            // only the chosen branch would ever run, so the other one can go
+            Expr::Literal(condition) => {
+                if is_truthy(condition.value) {
+                    fold_expr(*e.then_branch)
+                } else {
+                    fold_expr(*e.else_branch)
+                }
+            }
+            condition => Expr::Conditional(Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(fold_expr(*e.then_branch)),
+                else_branch: Box::new(fold_expr(*e.else_branch)),
+            }),
+        },
+        Expr::Function(e) => Expr::Function(Function {
+            name: e.name,
+            params: e
+                .params
+                .into_iter()
+                .map(|param| Param {
+                    name: param.name,
+                    default: param.default.map(fold_expr),
+                    rest: param.rest,
+                })
+                .collect(),
+            body: Rc::new(optimize((*e.body).clone())),
+        }),
+        Expr::Get(e) => Expr::Get(Get {
+            object: Box::new(fold_expr(*e.object)),
+            name: e.name,
+        }),
+        Expr::Grouping(e) => fold_expr(*e.expression),
+        Expr::Index(e) => Expr::Index(Index {
+            object: Box::new(fold_expr(*e.object)),
+            bracket: e.bracket,
+            index: Box::new(fold_expr(*e.index)),
+        }),
+        Expr::Interpolation(e) => {
+            let parts: Vec<Expr> = e.parts.into_iter().map(fold_expr).collect();
+            let folded = parts.iter().all(is_literal);
+            let expr = Expr::Interpolation(Interpolation { parts });
+            if folded {
+                fold_constant(expr)
+            } else {
+                expr
+            }
+        }
+        // lists, maps and sets are mutable, so each evaluation has to build a fresh one
+        Expr::List(e) => Expr::List(List {
+            elements: e.elements.into_iter().map(fold_expr).collect(),
+        }),
+        Expr::Literal(_) => expr,
+        Expr::Map(e) => Expr::Map(Map {
+            brace: e.brace,
+            entries: e
+                .entries
+                .into_iter()
+                .map(|(key, value)| (fold_expr(key), fold_expr(value)))
+                .collect(),
+        }),
+        Expr::Set(e) => Expr::Set(Set {
+            brace: e.brace,
+            elements: e.elements.into_iter().map(fold_expr).collect(),
+        }),
+        Expr::SetIndex(e) => Expr::SetIndex(SetIndex {
+            object: Box::new(fold_expr(*e.object)),
+            bracket: e.bracket,
+            index: Box::new(fold_expr(*e.index)),
+            value: Box::new(fold_expr(*e.value)),
+        }),
+        Expr::Slice(e) => Expr::Slice(Slice {
+            object: Box::new(fold_expr(*e.object)),
+            bracket: e.bracket,
+            start: e.start.map(|start| Box::new(fold_expr(*start))),
+            end: e.end.map(|end| Box::new(fold_expr(*end))),
+        }),
+        Expr::Unary(e) => {
+            let right = fold_expr(*e.right);
+            let folded = is_literal(&right);
+            let expr = Expr::Unary(Unary {
+                operator: e.operator,
+                right: Box::new(right),
+            });
+            if folded {
+                fold_constant(expr)
+            } else {
+                expr
+            }
+        }
+        // unlike lists, tuples are immutable and can be built once
+        Expr::Tuple(e) => {
+            let elements: Vec<Expr> = e.elements.into_iter().map(fold_expr).collect();
+            let folded = elements.iter().all(is_literal);
+            let expr = Expr::Tuple(Tuple { elements });
+            if folded {
+                fold_constant(expr)
+            } else {
+                expr
+            }
+        }
+        Expr::Variable(_) => expr,
+    }
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    matches!(expr, Expr::Literal(_))
+}
+
+/// Evaluates an expression whose operands are all literals. If evaluating it
+/// would fail, the expression is kept as-is so the error is still raised at
+/// run time, pointing at the original operator token.
+///
+/// This runs the operator at compile time, so no operator may take more than
+/// time proportional to its operands. `**` is the one that could, and it
+/// refuses results too large to compute quickly.
+fn fold_constant(expr: Expr) -> Expr {
+    // the interpreter's file is only used in stack traces, which are dropped,
+    // and constant expressions never call functions
+    match Interpreter::new("", 0).evaluate(&expr) {
+        Ok(value) => Expr::Literal(Literal { value }),
+        Err(_) => expr,
+    }
+}
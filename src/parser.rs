@@ -1,5 +1,12 @@
+use std::{collections::HashMap, rc::Rc};
+
 use crate::{
-    ast::{Binary, Expr, Expression, Grouping, Literal, Print, Stmt, Unary},
+    ast::{
+        Assign, Binary, Block, Call, Catch, Conditional, Expr, Expression, ForIn, Function, Get,
+        Grouping, If, Index, Interpolation, List, Literal, Map, Param, Print, Return, Set,
+        SetIndex, Slice, Stmt, Throw, Try, Tuple, Unary, Var, Variable, While,
+    },
+    interner::Symbol,
     scanner::{Object, Token, TokenType},
     Lox,
 };
@@ -8,35 +15,608 @@ pub struct Parser<'a> {
     lox: &'a mut Lox,
     tokens: &'a Vec<Token>,
     current: usize,
+    /// How many loops enclose the statement being parsed, so that `break`
+    /// and `continue` can be rejected outside of one.
+    loop_depth: usize,
+    /// How many functions enclose the statement being parsed, so that
+    /// `return` can be rejected at the top level.
+    function_depth: usize,
+    /// How deeply the syntax being parsed is nested. It's limited so that
+    /// neither the parser nor the interpreter runs out of stack.
+    depth: usize,
+    /// The names declared so far in each scope enclosing the code being
+    /// parsed, innermost last, and whether each is a constant. This is how
+    /// assignments to constants are caught before the program runs.
+    scopes: Vec<HashMap<Symbol, bool>>,
 }
 
 pub struct ParseError;
 
+/// Binding power of operators, from loosest to tightest.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Precedence {
+    None,
+    Comma,       // ,
+    Assignment,  // =
+    Conditional, // ?:
+    Equality,    // == !=
+    Comparison,  // < > <= >=
+    Range,       // .. ..=
+    BitOr,       // |
+    BitXor,      // ^
+    BitAnd,      // &
+    Shift,       // << >>
+    Term,        // + -
+    Factor,      // * / % ~/
+    Unary,       // ! - ~
+    Exponent,    // **
+    Call,        // . () []
+    Primary,
+}
+
+impl Precedence {
+    /// The next tighter precedence level, used for the right operand of
+    /// left-associative operators.
+    fn next(self) -> Precedence {
+        match self {
+            Precedence::None => Precedence::Comma,
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::Range,
+            Precedence::Range => Precedence::BitOr,
+            Precedence::BitOr => Precedence::BitXor,
+            Precedence::BitXor => Precedence::BitAnd,
+            Precedence::BitAnd => Precedence::Shift,
+            Precedence::Shift => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Exponent,
+            Precedence::Exponent => Precedence::Call,
+            Precedence::Call => Precedence::Primary,
+            Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+type PrefixFn = fn(&mut Parser) -> Result<Expr, ParseError>;
+type InfixFn = fn(&mut Parser, Expr) -> Result<Expr, ParseError>;
+
+struct ParseRule {
+    prefix: Option<PrefixFn>,
+    infix: Option<InfixFn>,
+    precedence: Precedence,
+    associativity: Associativity,
+}
+
+impl ParseRule {
+    fn new(
+        prefix: Option<PrefixFn>,
+        infix: Option<InfixFn>,
+        precedence: Precedence,
+        associativity: Associativity,
+    ) -> ParseRule {
+        ParseRule {
+            prefix,
+            infix,
+            precedence,
+            associativity,
+        }
+    }
+}
+
+const FUNCTION: PrefixFn = |parser| parser.function_expression();
+const GROUPING: PrefixFn = |parser| parser.grouping();
+const INTERPOLATION: PrefixFn = |parser| parser.interpolation();
+const LIST: PrefixFn = |parser| parser.list();
+const LITERAL: PrefixFn = |parser| parser.literal();
+const MAP_OR_SET: PrefixFn = |parser| parser.map_or_set();
+const UNARY: PrefixFn = |parser| parser.unary();
+const VARIABLE: PrefixFn = |parser| parser.variable();
+const BINARY: InfixFn = |parser, left| parser.binary(left);
+const ASSIGNMENT: InfixFn = |parser, left| parser.assignment(left);
+const CALL: InfixFn = |parser, left| parser.call(left);
+const CONDITIONAL: InfixFn = |parser, left| parser.conditional(left);
+const GET: InfixFn = |parser, left| parser.get(left);
+const INDEX: InfixFn = |parser, left| parser.index(left);
+
+/// The parse table: how each token behaves at the start of an expression
+/// (prefix) and after a complete operand (infix).
+fn get_rule(token_type: TokenType) -> ParseRule {
+    use Associativity::*;
+
+    match token_type {
+        TokenType::LeftParen => ParseRule::new(Some(GROUPING), Some(CALL), Precedence::Call, Left),
+        TokenType::LeftBrace => ParseRule::new(Some(MAP_OR_SET), None, Precedence::None, Left),
+        TokenType::LeftBracket => ParseRule::new(Some(LIST), Some(INDEX), Precedence::Call, Left),
+        TokenType::Dot => ParseRule::new(None, Some(GET), Precedence::Call, Left),
+        TokenType::Equal => ParseRule::new(None, Some(ASSIGNMENT), Precedence::Assignment, Right),
+        TokenType::Comma => ParseRule::new(None, Some(BINARY), Precedence::Comma, Left),
+        TokenType::Question => {
+            ParseRule::new(None, Some(CONDITIONAL), Precedence::Conditional, Right)
+        }
+        TokenType::Minus => ParseRule::new(Some(UNARY), Some(BINARY), Precedence::Term, Left),
+        TokenType::Plus => ParseRule::new(None, Some(BINARY), Precedence::Term, Left),
+        TokenType::Slash => ParseRule::new(None, Some(BINARY), Precedence::Factor, Left),
+        TokenType::Star => ParseRule::new(None, Some(BINARY), Precedence::Factor, Left),
+        TokenType::Percent => ParseRule::new(None, Some(BINARY), Precedence::Factor, Left),
+        TokenType::TildeSlash => ParseRule::new(None, Some(BINARY), Precedence::Factor, Left),
+        TokenType::StarStar => ParseRule::new(None, Some(BINARY), Precedence::Exponent, Right),
+        TokenType::Bang => ParseRule::new(Some(UNARY), None, Precedence::None, Left),
+        TokenType::Tilde => ParseRule::new(Some(UNARY), None, Precedence::None, Left),
+        TokenType::DotDot => ParseRule::new(None, Some(BINARY), Precedence::Range, Left),
+        TokenType::DotDotEqual => ParseRule::new(None, Some(BINARY), Precedence::Range, Left),
+        TokenType::Pipe => ParseRule::new(None, Some(BINARY), Precedence::BitOr, Left),
+        TokenType::Caret => ParseRule::new(None, Some(BINARY), Precedence::BitXor, Left),
+        TokenType::Ampersand => ParseRule::new(None, Some(BINARY), Precedence::BitAnd, Left),
+        TokenType::LessLess => ParseRule::new(None, Some(BINARY), Precedence::Shift, Left),
+        TokenType::GreaterGreater => ParseRule::new(None, Some(BINARY), Precedence::Shift, Left),
+        TokenType::BangEqual => ParseRule::new(None, Some(BINARY), Precedence::Equality, Left),
+        TokenType::EqualEqual => ParseRule::new(None, Some(BINARY), Precedence::Equality, Left),
+        TokenType::Greater => ParseRule::new(None, Some(BINARY), Precedence::Comparison, Left),
+        TokenType::GreaterEqual => ParseRule::new(None, Some(BINARY), Precedence::Comparison, Left),
+        TokenType::Less => ParseRule::new(None, Some(BINARY), Precedence::Comparison, Left),
+        TokenType::LessEqual => ParseRule::new(None, Some(BINARY), Precedence::Comparison, Left),
+        TokenType::Identifier => ParseRule::new(Some(VARIABLE), None, Precedence::None, Left),
+        TokenType::String => ParseRule::new(Some(LITERAL), None, Precedence::None, Left),
+        TokenType::Interpolation => {
+            ParseRule::new(Some(INTERPOLATION), None, Precedence::None, Left)
+        }
+        TokenType::Number => ParseRule::new(Some(LITERAL), None, Precedence::None, Left),
+        TokenType::False => ParseRule::new(Some(LITERAL), None, Precedence::None, Left),
+        TokenType::True => ParseRule::new(Some(LITERAL), None, Precedence::None, Left),
+        TokenType::Nil => ParseRule::new(Some(LITERAL), None, Precedence::None, Left),
+        TokenType::Fun => ParseRule::new(Some(FUNCTION), None, Precedence::None, Left),
+        _ => ParseRule::new(None, None, Precedence::None, Left),
+    }
+}
+
 impl Parser<'_> {
     pub fn new<'a>(tokens: &'a Vec<Token>, lox: &'a mut Lox) -> Parser<'a> {
         Parser {
             tokens,
             current: 0,
+            loop_depth: 0,
+            function_depth: 0,
+            depth: 0,
+            scopes: vec![HashMap::new()],
             lox,
         }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
         let mut statements: Vec<Stmt> = Vec::new();
+        let mut had_error = false;
         while !self.is_at_end() {
-            statements.push(self.statement()?)
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(_) => {
+                    had_error = true;
+                    self.synchronize();
+                }
+            }
+        }
+        if had_error {
+            Err(ParseError {})
+        } else {
+            Ok(statements)
+        }
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        // `fun` without a name starts an anonymous function expression
+        if self.check(TokenType::Fun) && self.check_next(TokenType::Identifier) {
+            let docs = self.advance().trivia.clone();
+            self.fun_declaration(docs)
+        } else if self.match_token_types(vec![TokenType::Var, TokenType::Const]) {
+            let docs = self.previous().trivia.clone();
+            self.var_declaration(docs)
+        } else {
+            self.statement()
+        }
+    }
+
+    /// Parses a named function after `fun`. It declares a variable holding
+    /// the function, just as `var name = fun (...) { ... };` would, except
+    /// that the function knows its name.
+    fn fun_declaration(&mut self, docs: Vec<Token>) -> Result<Stmt, ParseError> {
+        let name = self.advance().clone();
+        // declared first so that the function can call itself
+        self.declare(&name, false);
+        let function = self.function(Some(name.clone()))?;
+        Ok(Stmt::Var(Var {
+            name,
+            initializer: Some(Expr::Function(function)),
+            constant: false,
+            docs,
+        }))
+    }
+
+    /// Parses the parameters and body of a `fun` declaration or expression,
+    /// after its name if it has one.
+    fn function(&mut self, name: Option<Token>) -> Result<Function, ParseError> {
+        let message = match name {
+            Some(_) => "Expect '(' after function name.",
+            None => "Expect '(' after 'fun'.",
+        };
+        self.consume(TokenType::LeftParen, message.to_owned())?;
+        self.scoped(|parser| {
+            let params = parser.parameters()?;
+            parser.consume(
+                TokenType::LeftBrace,
+                "Expect '{' before function body.".to_owned(),
+            )?;
+            let body = parser.function_body(Parser::block_statements)?;
+            Ok(Function {
+                name,
+                params,
+                body: Rc::new(body),
+            })
+        })
+    }
+
+    /// Parses a parameter list after its `(`, up to and including the `)`.
+    /// Parameters with defaults come after those without, and a `...rest`
+    /// parameter comes last.
+    fn parameters(&mut self) -> Result<Vec<Param>, ParseError> {
+        let mut params: Vec<Param> = vec![];
+        while !self.check(TokenType::RightParen) {
+            if params.last().is_some_and(|param| param.rest) {
+                let token = self.peek().clone();
+                return Err(self.error(token, "Rest parameter must be last.".to_owned()));
+            }
+            let rest = self.match_token_types(vec![TokenType::DotDotDot]);
+            let name = self
+                .consume(TokenType::Identifier, "Expect parameter name.".to_owned())?
+                .clone();
+            let default = if self.match_token_types(vec![TokenType::Equal]) {
+                if rest {
+                    let equals = self.previous().clone();
+                    return Err(self.error(
+                        equals,
+                        "Rest parameter can't have a default value.".to_owned(),
+                    ));
+                }
+                Some(self.parse_precedence(Precedence::Assignment)?)
+            } else {
+                let follows_default = params.iter().any(|param| param.default.is_some());
+                if follows_default && !rest {
+                    return Err(self.error(
+                        name,
+                        "Parameter without a default can't follow one with a default.".to_owned(),
+                    ));
+                }
+                None
+            };
+            // later defaults can refer to earlier parameters
+            self.declare(&name, false);
+            params.push(Param {
+                name,
+                default,
+                rest,
+            });
+            if !self.match_token_types(vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after parameters.".to_owned(),
+        )?;
+        Ok(params)
+    }
+
+    /// Parses a function body with `parse`. Loops outside of the function
+    /// don't count inside it, so `break` can't jump out of a function.
+    fn function_body<T>(
+        &mut self,
+        parse: fn(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.function_depth += 1;
+        let body = parse(self);
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        body
+    }
+
+    /// Parses a `var` or `const` declaration after its keyword. Constants
+    /// have to be initialized, since they can't be assigned later. As in C,
+    /// the initializer stops at a comma rather than taking in a comma
+    /// expression.
+    fn var_declaration(&mut self, docs: Vec<Token>) -> Result<Stmt, ParseError> {
+        let constant = self.previous().token_type == TokenType::Const;
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.".to_owned())?
+            .clone();
+        let initializer = if constant {
+            self.consume(
+                TokenType::Equal,
+                "Expect '=' after constant name.".to_owned(),
+            )?;
+            Some(self.parse_precedence(Precedence::Assignment)?)
+        } else if self.match_token_types(vec![TokenType::Equal]) {
+            Some(self.parse_precedence(Precedence::Assignment)?)
+        } else {
+            None
+        };
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.".to_owned(),
+        )?;
+        self.declare(&name, constant);
+        Ok(Stmt::Var(Var {
+            name,
+            initializer,
+            constant,
+            docs,
+        }))
+    }
+
+    /// Parses with `parse` in a new scope, so that names it declares are
+    /// forgotten afterwards.
+    fn scoped<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.scopes.push(HashMap::new());
+        let result = parse(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn declare(&mut self, name: &Token, constant: bool) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.insert(name.lexeme.symbol(), constant) == Some(true) {
+            // the declaration itself parses fine, so there's no need to sync
+            self.error(
+                name.clone(),
+                format!("Can't redeclare constant '{}'.", name.lexeme),
+            );
         }
-        Ok(statements)
+    }
+
+    /// Whether `name` refers to a constant where it's used. Names that
+    /// haven't been declared yet might still be constants at run time, which
+    /// the interpreter checks for.
+    fn is_constant(&self, name: &Token) -> bool {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme.symbol()))
+            .is_some_and(|constant| *constant)
     }
 
     fn statement(&mut self) -> Result<Stmt, ParseError> {
-        if self.match_token_types(vec![TokenType::Print]) {
+        let depth = self.depth;
+        let result = self.nest().and_then(|()| self.statement_kind());
+        self.depth = depth;
+        result
+    }
+
+    fn statement_kind(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_token_types(vec![TokenType::Break, TokenType::Continue]) {
+            self.loop_jump_statement()
+        } else if self.match_token_types(vec![TokenType::For]) {
+            self.for_statement()
+        } else if self.match_token_types(vec![TokenType::If]) {
+            self.if_statement()
+        } else if self.match_token_types(vec![TokenType::Print]) {
             self.print_statement()
+        } else if self.match_token_types(vec![TokenType::Return]) {
+            self.return_statement()
+        } else if self.match_token_types(vec![TokenType::Throw]) {
+            self.throw_statement()
+        } else if self.match_token_types(vec![TokenType::Try]) {
+            self.try_statement()
+        } else if self.match_token_types(vec![TokenType::While]) {
+            self.while_statement()
+        } else if self.match_token_types(vec![TokenType::LeftBrace]) {
+            self.block()
         } else {
             self.expression_statement()
         }
     }
 
+    fn block(&mut self) -> Result<Stmt, ParseError> {
+        Ok(Stmt::Block(Block {
+            statements: self.block_statements()?,
+        }))
+    }
+
+    /// Parses the statements of a block up to and including its `}`.
+    fn block_statements(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        self.scoped(|parser| {
+            let mut statements = vec![];
+            while !parser.check(TokenType::RightBrace) && !parser.is_at_end() {
+                statements.push(parser.declaration()?);
+            }
+            parser.consume(TokenType::RightBrace, "Expect '}' after block.".to_owned())?;
+            Ok(statements)
+        })
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        if self.function_depth == 0 {
+            self.error(
+                keyword.clone(),
+                "Can't return from top-level code.".to_owned(),
+            );
+        }
+        let value = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after return value.".to_owned(),
+        )?;
+        Ok(Stmt::Return(Return { keyword, value }))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after thrown value.".to_owned(),
+        )?;
+        Ok(Stmt::Throw(Throw { keyword, value }))
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.".to_owned())?;
+        let body = self.block_statements()?;
+
+        let catch = if self.match_token_types(vec![TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.".to_owned())?;
+            let name = self
+                .consume(
+                    TokenType::Identifier,
+                    "Expect exception variable name.".to_owned(),
+                )?
+                .clone();
+            self.consume(
+                TokenType::RightParen,
+                "Expect ')' after exception variable.".to_owned(),
+            )?;
+            self.consume(
+                TokenType::LeftBrace,
+                "Expect '{' before catch body.".to_owned(),
+            )?;
+            let body = self.scoped(|parser| {
+                parser.declare(&name, false);
+                parser.block_statements()
+            })?;
+            Some(Catch { name, body })
+        } else {
+            None
+        };
+
+        let finally = if self.match_token_types(vec![TokenType::Finally]) {
+            self.consume(
+                TokenType::LeftBrace,
+                "Expect '{' after 'finally'.".to_owned(),
+            )?;
+            Some(self.block_statements()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            let token = self.peek().clone();
+            return Err(self.error(
+                token,
+                "Expect 'catch' or 'finally' after try block.".to_owned(),
+            ));
+        }
+        Ok(Stmt::Try(Try {
+            body,
+            catch,
+            finally,
+        }))
+    }
+
+    /// Parses a `break` or `continue` after its keyword.
+    fn loop_jump_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            // the statement itself parses fine, so there's no need to sync
+            self.error(
+                keyword.clone(),
+                format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+            );
+        }
+        self.consume(
+            TokenType::Semicolon,
+            format!("Expect ';' after '{}'.", keyword.lexeme),
+        )?;
+        if keyword.token_type == TokenType::Break {
+            Ok(Stmt::Break)
+        } else {
+            Ok(Stmt::Continue)
+        }
+    }
+
+    /// Parses `for (var name in iterable) body` after the `for`.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.".to_owned())?;
+        self.consume(TokenType::Var, "Expect 'var' after '('.".to_owned())?;
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.".to_owned())?
+            .clone();
+        let keyword = self
+            .consume(TokenType::In, "Expect 'in' after loop variable.".to_owned())?
+            .clone();
+        let iterable = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after for clauses.".to_owned(),
+        )?;
+        let body = self.scoped(|parser| {
+            parser.declare(&name, false);
+            parser.loop_body()
+        })?;
+        Ok(Stmt::ForIn(ForIn {
+            name,
+            keyword,
+            iterable,
+            body: Box::new(body),
+        }))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.".to_owned())?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after condition.".to_owned(),
+        )?;
+        let body = self.loop_body()?;
+        Ok(Stmt::While(While {
+            condition,
+            body: Box::new(body),
+        }))
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, ParseError> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.".to_owned())?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after if condition.".to_owned(),
+        )?;
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = if self.match_token_types(vec![TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+        Ok(Stmt::If(If {
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
     fn print_statement(&mut self) -> Result<Stmt, ParseError> {
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.".to_owned())?;
@@ -53,123 +633,399 @@ impl Parser<'_> {
     }
 
     fn expression(&mut self) -> Result<Expr, ParseError> {
-        self.equality()
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    /// Parses an expression whose operators all bind at least as tightly as
+    /// `precedence`.
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expr, ParseError> {
+        let depth = self.depth;
+        let result = self.parse_operators(precedence);
+        self.depth = depth;
+        result
     }
 
-    fn equality(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.comparison()?;
+    fn parse_operators(&mut self, precedence: Precedence) -> Result<Expr, ParseError> {
+        let prefix = match get_rule(self.peek().token_type).prefix {
+            Some(prefix) => prefix,
+            None => return Err(self.error(self.peek().clone(), "Expect expression.".to_owned())),
+        };
+        self.nest()?;
+        self.advance();
+        let mut expr = prefix(self)?;
 
-        while self.match_token_types(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = self.previous().clone();
-            let right = self.comparison()?;
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator: operator,
-                right: Box::new(right),
-            });
+        loop {
+            let rule = get_rule(self.peek().token_type);
+            match rule.infix {
+                Some(infix) if precedence <= rule.precedence => {
+                    // each operator nests the expression so far one level
+                    // deeper, even though parsing it doesn't recurse
+                    self.nest()?;
+                    self.advance();
+                    expr = infix(self, expr)?;
+                }
+                _ => return Ok(expr),
+            }
         }
+    }
 
-        Ok(expr)
+    /// Goes one level deeper into the syntax, failing at the next token if
+    /// that's too deep. Callers restore the depth when they're done.
+    fn nest(&mut self) -> Result<(), ParseError> {
+        if self.depth >= self.lox.max_nesting {
+            let token = self.peek().clone();
+            return Err(self.error(token, "Too much nesting.".to_owned()));
+        }
+        self.depth += 1;
+        Ok(())
     }
 
-    fn comparison(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.term()?;
+    fn binary(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+        let rule = get_rule(operator.token_type);
+        let right = match rule.associativity {
+            Associativity::Left => self.parse_precedence(rule.precedence.next())?,
+            Associativity::Right => self.parse_precedence(rule.precedence)?,
+        };
+        Ok(Expr::Binary(Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }))
+    }
 
-        while self.match_token_types(vec![
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let operator = self.previous().clone();
-            let right = self.term()?;
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator: operator,
-                right: Box::new(right),
-            });
+    fn assignment(&mut self, target: Expr) -> Result<Expr, ParseError> {
+        let equals = self.previous().clone();
+        let value = self.parse_precedence(Precedence::Assignment)?;
+        match target {
+            Expr::Variable(target) => {
+                if self.is_constant(&target.name) {
+                    // the assignment itself parses fine, so there's no need
+                    // to sync
+                    self.error(
+                        target.name.clone(),
+                        format!("Cannot assign to constant '{}'.", target.name.lexeme),
+                    );
+                }
+                Ok(Expr::Assign(Assign {
+                    name: target.name,
+                    value: Box::new(value),
+                }))
+            }
+            Expr::Index(target) => Ok(Expr::SetIndex(SetIndex {
+                object: target.object,
+                bracket: target.bracket,
+                index: target.index,
+                value: Box::new(value),
+            })),
+            _ => Err(self.error(equals, "Invalid assignment target.".to_owned())),
         }
+    }
+
+    fn call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = vec![];
+        let mut named_arguments = vec![];
+        while !self.check(TokenType::RightParen) {
+            if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+                let name = self.advance().clone();
+                self.advance();
+                named_arguments.push((name, self.parse_precedence(Precedence::Assignment)?));
+            } else if named_arguments.is_empty() {
+                arguments.push(self.parse_precedence(Precedence::Assignment)?);
+            } else {
+                let token = self.peek().clone();
+                return Err(self.error(
+                    token,
+                    "Positional argument can't follow named arguments.".to_owned(),
+                ));
+            }
+            if !self.match_token_types(vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        let paren = self
+            .consume(
+                TokenType::RightParen,
+                "Expect ')' after arguments.".to_owned(),
+            )?
+            .clone();
+        Ok(Expr::Call(Call {
+            callee: Box::new(callee),
+            paren,
+            arguments,
+            named_arguments,
+        }))
+    }
 
-        Ok(expr)
+    fn get(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let name = self
+            .consume(
+                TokenType::Identifier,
+                "Expect property name after '.'.".to_owned(),
+            )?
+            .clone();
+        Ok(Expr::Get(Get {
+            object: Box::new(object),
+            name,
+        }))
     }
 
-    fn term(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.factor()?;
+    /// Parses `object[index]` or a slice `object[start:end]`, after the `[`.
+    fn index(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let bracket = self.previous().clone();
+        let start = if self.check(TokenType::Colon) {
+            None
+        } else {
+            Some(Box::new(self.parse_precedence(Precedence::Assignment)?))
+        };
 
-        while self.match_token_types(vec![TokenType::Minus, TokenType::Plus]) {
-            let operator = self.previous().clone();
-            let right = self.factor()?;
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator: operator,
-                right: Box::new(right),
-            });
+        if self.match_token_types(vec![TokenType::Colon]) {
+            let end = if self.check(TokenType::RightBracket) {
+                None
+            } else {
+                Some(Box::new(self.parse_precedence(Precedence::Assignment)?))
+            };
+            self.consume(
+                TokenType::RightBracket,
+                "Expect ']' after slice.".to_owned(),
+            )?;
+            return Ok(Expr::Slice(Slice {
+                object: Box::new(object),
+                bracket,
+                start,
+                end,
+            }));
         }
 
-        Ok(expr)
+        self.consume(
+            TokenType::RightBracket,
+            "Expect ']' after index.".to_owned(),
+        )?;
+        Ok(Expr::Index(Index {
+            object: Box::new(object),
+            bracket,
+            // only a slice can leave out its start
+            index: start.unwrap(),
+        }))
     }
 
-    fn factor(&mut self) -> Result<Expr, ParseError> {
-        let mut expr = self.unary()?;
+    fn list(&mut self) -> Result<Expr, ParseError> {
+        let mut elements = vec![];
+        while !self.check(TokenType::RightBracket) {
+            elements.push(self.parse_precedence(Precedence::Assignment)?);
+            if !self.match_token_types(vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::RightBracket,
+            "Expect ']' after list elements.".to_owned(),
+        )?;
+        Ok(Expr::List(List { elements }))
+    }
 
-        while self.match_token_types(vec![TokenType::Slash, TokenType::Star]) {
-            let operator = self.previous().clone();
-            let right = self.factor()?;
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator: operator,
-                right: Box::new(right),
-            });
+    /// Parses a map or set literal after its `{`, telling them apart by
+    /// whether the first element is followed by a `:`. A `{` that starts a
+    /// statement is always a block, so a literal there has to be wrapped in
+    /// parentheses.
+    fn map_or_set(&mut self) -> Result<Expr, ParseError> {
+        let brace = self.previous().clone();
+        if self.match_token_types(vec![TokenType::Comma]) {
+            self.consume(
+                TokenType::RightBrace,
+                "Expect '}' after ',' in empty set.".to_owned(),
+            )?;
+            return Ok(Expr::Set(Set {
+                brace,
+                elements: vec![],
+            }));
+        }
+        let mut entries = vec![];
+        while !self.check(TokenType::RightBrace) {
+            let key = self.parse_precedence(Precedence::Assignment)?;
+            if entries.is_empty() && !self.check(TokenType::Colon) {
+                return self.set(brace, key);
+            }
+            self.consume(TokenType::Colon, "Expect ':' after map key.".to_owned())?;
+            let value = self.parse_precedence(Precedence::Assignment)?;
+            entries.push((key, value));
+            if !self.match_token_types(vec![TokenType::Comma]) {
+                break;
+            }
         }
+        self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after map entries.".to_owned(),
+        )?;
+        Ok(Expr::Map(Map { brace, entries }))
+    }
+
+    fn set(&mut self, brace: Token, first: Expr) -> Result<Expr, ParseError> {
+        let mut elements = vec![first];
+        while self.match_token_types(vec![TokenType::Comma]) {
+            if self.check(TokenType::RightBrace) {
+                break;
+            }
+            elements.push(self.parse_precedence(Precedence::Assignment)?);
+        }
+        self.consume(
+            TokenType::RightBrace,
+            "Expect '}' after set elements.".to_owned(),
+        )?;
+        Ok(Expr::Set(Set { brace, elements }))
+    }
 
-        Ok(expr)
+    fn variable(&mut self) -> Result<Expr, ParseError> {
+        Ok(Expr::Variable(Variable {
+            name: self.previous().clone(),
+        }))
+    }
+
+    fn conditional(&mut self, condition: Expr) -> Result<Expr, ParseError> {
+        let then_branch = self.expression()?;
+        self.consume(
+            TokenType::Colon,
+            "Expect ':' after then branch of conditional.".to_owned(),
+        )?;
+        let else_branch = self.parse_precedence(Precedence::Conditional)?;
+        Ok(Expr::Conditional(Conditional {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        }))
     }
 
     fn unary(&mut self) -> Result<Expr, ParseError> {
-        while self.match_token_types(vec![TokenType::Bang, TokenType::Minus]) {
-            let operator = self.previous().clone();
-            let right = self.unary()?;
-            return Ok(Expr::Unary(Unary {
-                operator: operator,
-                right: Box::new(right),
-            }));
+        let operator = self.previous().clone();
+        let right = self.parse_precedence(Precedence::Unary)?;
+        Ok(Expr::Unary(Unary {
+            operator,
+            right: Box::new(right),
+        }))
+    }
+
+    /// Whether the `(` just consumed starts an arrow lambda's parameter list,
+    /// which is the case if the matching `)` is followed by `=>`. Default
+    /// values can hold any expression, so this has to skip to the `)` rather
+    /// than look for a list of names.
+    fn is_lambda(&self) -> bool {
+        let mut depth = 0;
+        for (current, token) in self.tokens.iter().enumerate().skip(self.current) {
+            match token.token_type {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen if depth > 0 => depth -= 1,
+                TokenType::RightParen => {
+                    return self.tokens[current + 1].token_type == TokenType::EqualGreater
+                }
+                TokenType::Eof => return false,
+                _ => (),
+            }
         }
+        false
+    }
 
-        self.primary()
+    /// Parses `(params) => body` after the `(`. The body is either a block or
+    /// a single expression whose value is returned.
+    fn lambda(&mut self) -> Result<Expr, ParseError> {
+        self.scoped(|parser| {
+            let params = parser.parameters()?;
+            let arrow = parser.advance().clone();
+            let body = if parser.match_token_types(vec![TokenType::LeftBrace]) {
+                parser.function_body(Parser::block_statements)?
+            } else {
+                let value = parser
+                    .function_body(|parser| parser.parse_precedence(Precedence::Assignment))?;
+                vec![Stmt::Return(Return {
+                    keyword: arrow,
+                    value: Some(value),
+                })]
+            };
+            Ok(Expr::Function(Function {
+                name: None,
+                params,
+                body: Rc::new(body),
+            }))
+        })
     }
 
-    fn primary(&mut self) -> Result<Expr, ParseError> {
-        if self.match_token_types(vec![TokenType::False]) {
-            return Ok(Expr::Literal(Literal {
-                value: Object::Bool(false),
-            }));
-        }
-        if self.match_token_types(vec![TokenType::True]) {
-            return Ok(Expr::Literal(Literal {
-                value: Object::Bool(true),
-            }));
-        }
-        if self.match_token_types(vec![TokenType::Nil]) {
-            return Ok(Expr::Literal(Literal { value: Object::Nil }));
+    fn function_expression(&mut self) -> Result<Expr, ParseError> {
+        Ok(Expr::Function(self.function(None)?))
+    }
+
+    /// Parses a parenthesized expression, or a tuple if the parentheses are
+    /// empty or hold a comma, as in `()`, `(1,)` and `(1, 2)`.
+    fn grouping(&mut self) -> Result<Expr, ParseError> {
+        if self.is_lambda() {
+            return self.lambda();
         }
-        if self.match_token_types(vec![TokenType::Number, TokenType::String]) {
-            return Ok(Expr::Literal(Literal {
-                value: self.previous().literal.clone().unwrap(),
-            }));
+        if self.match_token_types(vec![TokenType::RightParen]) {
+            return Ok(Expr::Tuple(Tuple { elements: vec![] }));
         }
-        if self.match_token_types(vec![TokenType::LeftParen]) {
-            let expr = self.expression()?;
-            return match self.consume(
+        let expr = self.parse_precedence(Precedence::Assignment)?;
+        if !self.match_token_types(vec![TokenType::Comma]) {
+            self.consume(
                 TokenType::RightParen,
                 "Expect ')' after expression.".to_owned(),
-            ) {
-                Ok(_) => Ok(Expr::Grouping(Grouping {
-                    expression: Box::new(expr),
-                })),
-                Err(e) => Err(e),
-            };
+            )?;
+            return Ok(Expr::Grouping(Grouping {
+                expression: Box::new(expr),
+            }));
         }
-        Err(self.error(self.peek().clone(), "Expect expression.".to_owned()))
+
+        let mut elements = vec![expr];
+        while !self.check(TokenType::RightParen) {
+            elements.push(self.parse_precedence(Precedence::Assignment)?);
+            if !self.match_token_types(vec![TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after tuple elements.".to_owned(),
+        )?;
+        Ok(Expr::Tuple(Tuple { elements }))
+    }
+
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let mut parts = vec![];
+        loop {
+            let segment = self.previous().literal.clone().unwrap();
+            parts.push(Expr::Literal(Literal { value: segment }));
+            if self.check(TokenType::RightBrace) {
+                let token = self.peek().clone();
+                return Err(self.error(
+                    token,
+                    "Expect expression in string interpolation.".to_owned(),
+                ));
+            }
+            parts.push(self.expression()?);
+            self.consume(
+                TokenType::RightBrace,
+                "Expect '}' after interpolated expression.".to_owned(),
+            )?;
+            // the scanner resumes the string after the `}`, up to its end or
+            // the next interpolation
+            if !self.match_token_types(vec![TokenType::Interpolation]) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::String,
+            "Expect end of string after interpolation.".to_owned(),
+        )?;
+        let segment = self.previous().literal.clone().unwrap();
+        parts.push(Expr::Literal(Literal { value: segment }));
+        Ok(Expr::Interpolation(Interpolation { parts }))
+    }
+
+    fn literal(&mut self) -> Result<Expr, ParseError> {
+        let value = match self.previous().token_type {
+            TokenType::False => Object::Bool(false),
+            TokenType::True => Object::Bool(true),
+            TokenType::Nil => Object::Nil,
+            _ => self.previous().literal.clone().unwrap(),
+        };
+        Ok(Expr::Literal(Literal { value }))
     }
 
     fn synchronize(&mut self) {
@@ -183,11 +1039,14 @@ impl Parser<'_> {
                 TokenType::Class
                 | TokenType::Fun
                 | TokenType::Var
+                | TokenType::Const
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => return,
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try => return,
                 _ => (),
             }
             self.advance();
@@ -230,13 +1089,20 @@ impl Parser<'_> {
         if !self.is_at_end() {
             self.current += 1
         }
-        return self.previous();
+        self.previous()
     }
 
     fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::Eof
     }
 
+    fn check_next(&self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn peek(&self) -> &Token {
         &self.tokens[self.current]
     }
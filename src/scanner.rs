@@ -1,8 +1,20 @@
 use core::fmt;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::string::String;
 
+use bigdecimal::BigDecimal;
+use indexmap::{IndexMap, IndexSet};
+use num_bigint::BigInt;
 use phf::phf_map;
+use unicode_normalization::UnicodeNormalization;
+use unicode_xid::UnicodeXID;
 
+use crate::builtins::{BuiltinFunction, BuiltinMethod, ErrorObject, Range};
+use crate::function::Closure;
+use crate::interner::{LoxString, Symbol};
+use crate::map::MapKey;
+use crate::number;
 use crate::Lox;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -12,44 +24,73 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
+    Colon,
+    Question,
     Minus,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     // One or two character tokens.
     Bang,
     BangEqual,
     Equal,
     EqualEqual,
+    EqualGreater,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
+    StarStar,
+    TildeSlash,
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
 
     // Literals.
     Identifier,
     String,
+    Interpolation,
     Number,
 
+    // Trivia.
+    DocComment,
+
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
+    Const,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    In,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -58,18 +99,78 @@ pub enum TokenType {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Object {
-    Str(String),
+    Str(LoxString),
+    Int(i64),
+    BigInt(BigInt),
+    Decimal(BigDecimal),
     F64(f64),
     Bool(bool),
     Nil,
+    List(Rc<RefCell<Vec<Object>>>),
+    Map(Rc<RefCell<IndexMap<MapKey, Object>>>),
+    Set(Rc<RefCell<IndexSet<MapKey>>>),
+    Tuple(Rc<Vec<Object>>),
+    Range(Range),
+    Error(Rc<ErrorObject>),
+    Closure(Rc<Closure>),
+    BuiltinMethod(Rc<BuiltinMethod>),
+    BuiltinFunction(Rc<BuiltinFunction>),
+    /// The value of the global `done`, which an iterator's `next` function
+    /// returns when it has no more values.
+    Done,
+}
+
+/// The source text of a token. Names, keywords and punctuation are interned,
+/// since the same few recur throughout a program. Number, string and comment
+/// lexemes are nearly all different, so they are kept as plain text rather
+/// than held by the interner for the rest of the run.
+#[derive(Clone, PartialEq)]
+pub enum Lexeme {
+    Symbol(Symbol),
+    Text(Rc<str>),
+}
+
+impl Lexeme {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Lexeme::Symbol(symbol) => symbol.as_str(),
+            Lexeme::Text(text) => text,
+        }
+    }
+
+    /// The interned name of an identifier. Only literal tokens have a
+    /// `Text` lexeme, and nothing looks those up by name.
+    pub fn symbol(&self) -> Symbol {
+        match self {
+            Lexeme::Symbol(symbol) => *symbol,
+            Lexeme::Text(text) => panic!("'{text}' is not a name"),
+        }
+    }
+}
+
+impl fmt::Display for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
 }
 
 #[derive(Clone)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    pub lexeme: Lexeme,
     pub literal: Option<Object>,
     pub line: i32,
+    /// The 1-based column, in characters, where the token starts.
+    pub column: i32,
+    /// `///` doc comments immediately preceding this token. The parser moves
+    /// those on the first token of a declaration onto its `Var`.
+    pub trivia: Vec<Token>,
 }
 
 impl fmt::Display for Token {
@@ -84,19 +185,27 @@ impl fmt::Display for Token {
 
 static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and" =>    TokenType::And,
+    "break" =>  TokenType::Break,
+    "catch" =>  TokenType::Catch,
     "class" =>  TokenType::Class,
+    "const" =>  TokenType::Const,
+    "continue" => TokenType::Continue,
     "else" =>   TokenType::Else,
     "false" =>  TokenType::False,
+    "finally" => TokenType::Finally,
     "for" =>    TokenType::For,
     "fun" =>    TokenType::Fun,
     "if" =>     TokenType::If,
+    "in" =>     TokenType::In,
     "nil" =>    TokenType::Nil,
     "or" =>     TokenType::Or,
     "print" =>  TokenType::Print,
     "return" => TokenType::Return,
     "super" =>  TokenType::Super,
     "this" =>   TokenType::This,
+    "throw" =>  TokenType::Throw,
     "true" =>   TokenType::True,
+    "try" =>    TokenType::Try,
     "var" =>    TokenType::Var,
     "while" =>  TokenType::While,
 };
@@ -107,6 +216,17 @@ pub struct Scanner<'a> {
     start: usize,
     current: usize,
     line: i32,
+    /// How many characters of the current line have been consumed. Columns
+    /// are counted as the scanner goes, since working them out from the
+    /// source for each token would take time proportional to the line.
+    column: usize,
+    /// What `column` was where the current lexeme starts.
+    start_column: usize,
+    /// For each string interpolation being scanned, innermost last, how many
+    /// braces inside its expression are still open.
+    interpolations: Vec<usize>,
+    /// Doc comments waiting to be attached to the next token.
+    trivia: Vec<Token>,
     lox: &'a mut Lox,
 }
 
@@ -117,26 +237,37 @@ impl Scanner<'_> {
             tokens: vec![],
             start: 0,
             current: 0,
-            line: 0,
+            line: 1,
+            column: 0,
+            start_column: 0,
+            interpolations: vec![],
+            trivia: vec![],
             lox,
         }
     }
 
     fn is_at_end(&self) -> bool {
-        self.current >= self.source.chars().count()
+        self.current >= self.source.len()
     }
 
     pub fn scan_tokens(&mut self) -> &Vec<Token> {
         while !self.is_at_end() {
-            self.start = self.current;
+            self.begin_lexeme();
             self.scan_token();
         }
+        if !self.interpolations.is_empty() {
+            self.lox
+                .report(self.line, "Unterminated string interpolation.".to_owned());
+        }
 
+        self.begin_lexeme();
         self.tokens.push(Token {
             token_type: TokenType::Eof,
-            lexeme: "".to_string(),
+            lexeme: Lexeme::Symbol(Symbol::intern("")),
             literal: None,
             line: self.line,
+            column: self.lexeme_column(),
+            trivia: std::mem::take(&mut self.trivia),
         });
         &self.tokens
     }
@@ -145,15 +276,69 @@ impl Scanner<'_> {
         let c = self.advance();
         match c {
             '(' => self.add_token(TokenType::LeftParen, None),
+            '[' => self.add_token(TokenType::LeftBracket, None),
+            ']' => self.add_token(TokenType::RightBracket, None),
             ')' => self.add_token(TokenType::RightParen, None),
-            '{' => self.add_token(TokenType::LeftBrace, None),
-            '}' => self.add_token(TokenType::RightBrace, None),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace, None)
+            }
+            '}' => match self.interpolations.last_mut() {
+                // this closes an interpolated expression, so pick the string
+                // back up after it
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.add_token(TokenType::RightBrace, None);
+                    self.begin_lexeme();
+                    self.string()
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RightBrace, None)
+                }
+                None => self.add_token(TokenType::RightBrace, None),
+            },
             ',' => self.add_token(TokenType::Comma, None),
-            '.' => self.add_token(TokenType::Dot, None),
+            '.' => {
+                let token_type = if !self.match_next('.') {
+                    TokenType::Dot
+                } else if self.match_next('=') {
+                    TokenType::DotDotEqual
+                } else if self.match_next('.') {
+                    TokenType::DotDotDot
+                } else {
+                    TokenType::DotDot
+                };
+                self.add_token(token_type, None)
+            }
+            ':' => self.add_token(TokenType::Colon, None),
+            '?' => self.add_token(TokenType::Question, None),
             '-' => self.add_token(TokenType::Minus, None),
             '+' => self.add_token(TokenType::Plus, None),
             ';' => self.add_token(TokenType::Semicolon, None),
-            '*' => self.add_token(TokenType::Star, None),
+            '%' => self.add_token(TokenType::Percent, None),
+            '*' => {
+                let token_type = if self.match_next('*') {
+                    TokenType::StarStar
+                } else {
+                    TokenType::Star
+                };
+                self.add_token(token_type, None)
+            }
+            '&' => self.add_token(TokenType::Ampersand, None),
+            '|' => self.add_token(TokenType::Pipe, None),
+            '^' => self.add_token(TokenType::Caret, None),
+            '~' => {
+                // floor division is spelled `~/` since `//` starts a comment
+                let token_type = if self.match_next('/') {
+                    TokenType::TildeSlash
+                } else {
+                    TokenType::Tilde
+                };
+                self.add_token(token_type, None)
+            }
             '!' => {
                 let token_type = if self.match_next('=') {
                     TokenType::BangEqual
@@ -165,6 +350,8 @@ impl Scanner<'_> {
             '=' => {
                 let token_type = if self.match_next('=') {
                     TokenType::EqualEqual
+                } else if self.match_next('>') {
+                    TokenType::EqualGreater
                 } else {
                     TokenType::Equal
                 };
@@ -173,6 +360,8 @@ impl Scanner<'_> {
             '<' => {
                 let token_type = if self.match_next('=') {
                     TokenType::LessEqual
+                } else if self.match_next('<') {
+                    TokenType::LessLess
                 } else {
                     TokenType::Less
                 };
@@ -181,6 +370,8 @@ impl Scanner<'_> {
             '>' => {
                 let token_type = if self.match_next('=') {
                     TokenType::GreaterEqual
+                } else if self.match_next('>') {
+                    TokenType::GreaterGreater
                 } else {
                     TokenType::Greater
                 };
@@ -188,18 +379,26 @@ impl Scanner<'_> {
             }
             '/' => {
                 if self.match_next('/') {
+                    // `///` is a doc comment, but `////` is just a comment
+                    let is_doc = self.peek() == '/' && self.peek_next() != '/';
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if is_doc {
+                        self.doc_comment()
+                    }
+                } else if self.match_next('*') {
+                    self.block_comment()
                 } else {
                     self.add_token(TokenType::Slash, None)
                 }
             }
             '"' => self.string(),
+            'r' if self.match_next('"') => self.raw_string(),
             ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
             _ if c.is_ascii_digit() => self.number(),
-            _ if c.is_ascii_alphabetic() => self.identifier(),
+            _ if is_identifier_start(c) => self.identifier(),
             _ => self
                 .lox
                 .report(self.line, "Unexpected character.".to_owned()),
@@ -207,54 +406,130 @@ impl Scanner<'_> {
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source.chars().nth(self.current);
-        self.current += 1;
-        match c {
-            Some(c) => c,
+        match self.source[self.current..].chars().next() {
+            Some(c) => {
+                self.current += c.len_utf8();
+                self.column += 1;
+                c
+            }
             None => panic!("Scanner failed at line {}", self.line),
         }
     }
 
     fn match_next(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        }
-        let c = self.source.chars().nth(self.current);
-        match c {
-            Some(c) => {
-                if c != expected {
-                    false
-                } else {
-                    self.current += 1;
-                    true
-                }
-            }
-            None => panic!("Scanner failed at line {}", self.line),
+        if self.is_at_end() || self.peek() != expected {
+            false
+        } else {
+            self.current += expected.len_utf8();
+            self.column += 1;
+            true
         }
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current).unwrap()
-        }
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current + 1).unwrap()
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
+    }
+
+    /// The source text of the token currently being scanned.
+    fn lexeme(&self) -> &str {
+        &self.source[self.start..self.current]
+    }
+
+    /// Starts a new lexeme at the next character.
+    fn begin_lexeme(&mut self) {
+        self.start = self.current;
+        self.start_column = self.column;
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 0;
+    }
+
+    /// The 1-based column of the first character of the current lexeme.
+    fn lexeme_column(&self) -> i32 {
+        self.start_column as i32 + 1
+    }
+
+    fn doc_comment(&mut self) {
+        let text = self.lexeme()[3..].strip_prefix(' ');
+        let text = text.unwrap_or(&self.lexeme()[3..]);
+        self.trivia.push(Token {
+            token_type: TokenType::DocComment,
+            lexeme: Lexeme::Text(Rc::from(self.lexeme())),
+            literal: Some(Object::Str(LoxString::from(text))),
+            line: self.line,
+            column: self.lexeme_column(),
+            trivia: vec![],
+        })
+    }
+
+    /// Skips a `/* ... */` comment, which may contain nested block comments.
+    fn block_comment(&mut self) {
+        let start_line = self.line;
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.lox
+                    .report(start_line, "Unterminated block comment.".to_owned());
+                return;
+            }
+            match self.advance() {
+                '/' if self.match_next('*') => depth += 1,
+                '*' if self.match_next('/') => depth -= 1,
+                '\n' => self.new_line(),
+                _ => (),
+            }
         }
     }
 
     fn string(&mut self) {
+        let mut value = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1
-            };
-            self.advance();
+            match self.advance() {
+                '\\' => {
+                    if let Some(c) = self.escape() {
+                        value.push(c)
+                    }
+                }
+                '\n' => {
+                    self.new_line();
+                    value.push('\n')
+                }
+                '$' if self.match_next('{') => {
+                    // the expression is scanned as ordinary tokens up to the
+                    // matching `}`, where `scan_token` resumes the string
+                    self.interpolations.push(0);
+                    let value = LoxString::Interned(Symbol::intern(&value));
+                    self.add_literal(TokenType::Interpolation, Object::Str(value));
+                    return;
+                }
+                c => value.push(c),
+            }
+        }
+
+        if self.is_at_end() {
+            self.lox
+                .report(self.line, "Unterminated string.".to_owned());
+            return;
+        }
+
+        self.advance();
+        let value = LoxString::Interned(Symbol::intern(&value));
+        self.add_literal(TokenType::String, Object::Str(value));
+    }
+
+    /// Scans a string prefixed with `r`, in which backslashes are ordinary
+    /// characters.
+    fn raw_string(&mut self) {
+        while self.peek() != '"' && !self.is_at_end() {
+            if self.advance() == '\n' {
+                self.new_line()
+            }
         }
 
         if self.is_at_end() {
@@ -264,71 +539,211 @@ impl Scanner<'_> {
         }
 
         self.advance();
-        let value: String = self
-            .source
-            .chars()
-            .skip(self.start + 1)
-            .take(self.current - 1 - (self.start + 1))
-            .collect();
-        self.add_token(TokenType::String, Some(Object::Str(value)));
+        let value = Symbol::intern(&self.source[self.start + 2..self.current - 1]);
+        self.add_literal(TokenType::String, Object::Str(LoxString::Interned(value)));
+    }
+
+    /// Decodes the escape sequence after a backslash, reporting it and
+    /// returning `None` if it is not a valid one.
+    fn escape(&mut self) -> Option<char> {
+        let column = self.column;
+        if self.is_at_end() {
+            return None;
+        }
+        match self.advance() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '$' => Some('$'),
+            'u' => self.unicode_escape(column),
+            c => {
+                if c == '\n' {
+                    self.new_line();
+                }
+                self.lox.report(
+                    self.line,
+                    format!(
+                        "Unknown escape sequence '\\{}' at column {column}.",
+                        c.escape_default()
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    /// Decodes a `\u{...}` escape of one to six hex digits, after the `u`.
+    fn unicode_escape(&mut self, column: usize) -> Option<char> {
+        let mut code_point = None;
+        if self.match_next('{') {
+            let digits_start = self.current;
+            while self.peek().is_ascii_hexdigit() {
+                self.advance();
+            }
+            let digits = &self.source[digits_start..self.current];
+            if (1..=6).contains(&digits.len()) && self.match_next('}') {
+                code_point = u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32);
+            }
+        }
+        if code_point.is_none() {
+            self.lox.report(
+                self.line,
+                format!("Invalid unicode escape sequence at column {column}."),
+            );
+        }
+        code_point
     }
 
     fn number(&mut self) {
-        while self.peek().is_ascii_digit() {
-            self.advance();
+        match self.number_value() {
+            Ok(value) => self.add_literal(TokenType::Number, value),
+            Err(message) => {
+                self.lox.report(self.line, message);
+                // skip the rest of the literal and stand in for it, so the
+                // parser doesn't report a missing expression on top
+                while is_identifier_char(self.peek()) {
+                    self.advance();
+                }
+                self.add_literal(TokenType::Number, Object::Int(0))
+            }
         }
+    }
+
+    /// Scans the rest of a number literal after its first digit, returning
+    /// the message to report if it is malformed.
+    fn number_value(&mut self) -> Result<Object, String> {
+        if self.lexeme() == "0" {
+            match self.peek() {
+                'x' | 'X' => return self.radix_number(16, "hexadecimal"),
+                'o' | 'O' => return self.radix_number(8, "octal"),
+                'b' | 'B' => return self.radix_number(2, "binary"),
+                _ => (),
+            }
+        }
+
+        self.digits(10)?;
+        let mut is_float = false;
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
-
-            while self.peek().is_ascii_digit() {
+            self.digits(10)?;
+            is_float = true;
+        }
+        if self.peek() == 'e' || self.peek() == 'E' {
+            self.advance();
+            if self.peek() == '+' || self.peek() == '-' {
                 self.advance();
             }
+            if !self.peek().is_ascii_digit() {
+                return Err("Expect digits in exponent.".to_owned());
+            }
+            self.digits(10)?;
+            is_float = true;
+        }
+
+        let text = self.lexeme().replace('_', "");
+        // a trailing `d` makes an exact decimal, as in `1.10d`
+        if self.peek() == 'd' && !is_identifier_char(self.peek_next()) {
+            self.advance();
+            // the only way well-formed digits fail to parse is an exponent
+            // that doesn't fit in 64 bits
+            text.parse::<BigDecimal>()
+                .map(Object::Decimal)
+                .map_err(|_| "Exponent too large.".to_owned())
+        } else if is_float {
+            // an exponent too large for a float just makes it infinite
+            text.parse::<f64>()
+                .map(Object::F64)
+                .map_err(|_| "Invalid number literal.".to_owned())
+        } else {
+            text.parse::<BigInt>()
+                .map(number::from_bigint)
+                .map_err(|_| "Invalid number literal.".to_owned())
         }
-        let value: String = self
-            .source
-            .chars()
-            .skip(self.start)
-            .take(self.current - self.start)
-            .collect();
-        self.add_token(
-            TokenType::Number,
-            Some(Object::F64(value.as_str().parse::<f64>().unwrap())),
-        )
+    }
+
+    /// Scans an integer literal such as `0xFF`, `0o17` or `0b1010`, starting
+    /// at the prefix letter.
+    fn radix_number(&mut self, radix: u32, name: &str) -> Result<Object, String> {
+        self.advance();
+        if !self.peek().is_digit(radix) {
+            return Err(format!("Expect digits in {name} literal."));
+        }
+        self.digits(radix)?;
+        if is_identifier_char(self.peek()) {
+            let digit = self.peek();
+            return Err(format!("Invalid digit '{digit}' in {name} literal."));
+        }
+
+        let text = self.lexeme()[2..].replace('_', "");
+        BigInt::parse_bytes(text.as_bytes(), radix)
+            .map(number::from_bigint)
+            .ok_or_else(|| format!("Invalid {name} literal."))
+    }
+
+    /// Consumes a run of digits in `radix`, which may be broken up by `_`
+    /// separators. Fails on a separator that is not followed by a digit.
+    fn digits(&mut self, radix: u32) -> Result<(), String> {
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            if self.advance() == '_' && !self.peek().is_digit(radix) && self.peek() != '_' {
+                return Err("Expect digit after '_' in number literal.".to_owned());
+            }
+        }
+        Ok(())
     }
 
     fn identifier(&mut self) {
-        while self.peek().is_ascii_alphanumeric() {
+        while is_identifier_char(self.peek()) {
             self.advance();
         }
 
-        let text: String = self
-            .source
-            .chars()
-            .skip(self.start)
-            .take(self.current - self.start)
-            .collect();
-
-        let token_type = if KEYWORDS.contains_key(text.as_str()) {
-            *KEYWORDS.get(text.as_str()).unwrap()
+        // normalize so that identifiers which look the same are the same,
+        // whichever combination of code points spelled them
+        let name = if self.lexeme().is_ascii() {
+            Symbol::intern(self.lexeme())
         } else {
-            TokenType::Identifier
+            Symbol::intern(&self.lexeme().nfc().collect::<String>())
+        };
+
+        let token_type = match KEYWORDS.get(name.as_str()) {
+            Some(&keyword) => keyword,
+            None => TokenType::Identifier,
         };
 
-        self.add_token(token_type, None)
+        self.push_token(token_type, Lexeme::Symbol(name), None)
     }
 
     fn add_token(&mut self, token_type: TokenType, literal: Option<Object>) {
-        let text: String = self
-            .source
-            .chars()
-            .skip(self.start)
-            .take(self.current - self.start)
-            .collect();
+        let lexeme = Lexeme::Symbol(Symbol::intern(self.lexeme()));
+        self.push_token(token_type, lexeme, literal)
+    }
+
+    /// Adds a string or number token, whose lexeme isn't interned.
+    fn add_literal(&mut self, token_type: TokenType, literal: Object) {
+        let lexeme = Lexeme::Text(Rc::from(self.lexeme()));
+        self.push_token(token_type, lexeme, Some(literal))
+    }
+
+    fn push_token(&mut self, token_type: TokenType, lexeme: Lexeme, literal: Option<Object>) {
         self.tokens.push(Token {
             token_type,
-            lexeme: text,
+            lexeme,
             literal,
             line: self.line,
+            column: self.lexeme_column(),
+            trivia: std::mem::take(&mut self.trivia),
         })
     }
 }
+
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c.is_xid_start()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_xid_continue()
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{run, Output};
+
+/// Runs `source` with and without constant folding, checking that both give
+/// the same output, errors and exit code.
+fn run_both(name: &str, source: &str) -> Output {
+    let folded = run(name, source, &[]);
+    let unfolded = run(name, source, &["--no-fold"]);
+    assert_eq!(folded, unfolded, "{source}");
+    folded
+}
+
+#[test]
+fn folding_keeps_values() {
+    let output = run_both(
+        "values",
+        r#"
+        print 1 + 2 * 3 - 4 / 2;
+        print 2 ** 10 % 7;
+        print 7 ~/ 2;
+        print 0.1 + 0.2;
+        print 1.5d + 2;
+        print 9223372036854775807 + 1;
+        print "a" + "b" + "c";
+        print "${1 + 2} and ${"x" + "y"}";
+        print !nil == true;
+        print 1 < 2 ? "yes" : "no";
+        print (1, 2 + 3) == (1, 5);
+        print 6 & 3 | 8 ^ 1 << 2;
+        print (1, 2);
+        "#,
+    );
+    assert_eq!(
+        output.stdout,
+        "5.0\n2\n3\n0.30000000000000004\n3.5\n9223372036854775808\nabc\n3 and xy\n\
+         true\nyes\ntrue\n14\n(1, 2)\n"
+    );
+}
+
+#[test]
+fn folding_keeps_runtime_errors() {
+    for (name, source) in [
+        ("negate", "print 1;\nprint -\"x\";"),
+        ("divide", "print 1 ~/ 0;"),
+        ("add", "print 1 + nil;"),
+        ("mix", "print 1.0d + 1.0;"),
+        ("shift", "print 1 << 64;"),
+        ("power", "print 1;\nvar x = 10 ** 4000000000;"),
+    ] {
+        let output = run_both(name, source);
+        assert_eq!(output.code, Some(70), "{source}");
+    }
+}
+
+#[test]
+fn folding_keeps_error_positions() {
+    let output = run_both("position", "print 1;\nprint  -\"x\";");
+    assert_eq!(output.stdout, "1\n");
+    assert!(output.stderr.starts_with("Operand must be a number.\n"));
+    assert!(
+        output.stderr.contains(":2:8] in script"),
+        "{}",
+        output.stderr
+    );
+}
@@ -0,0 +1,416 @@
+mod common;
+
+use std::{
+    env, fs,
+    process::{self, Command},
+};
+
+use common::{run, run_prompt};
+
+#[test]
+fn arithmetic_operators_are_left_associative() {
+    // `/` always gives a float, so 8 / 4 / 2 is 1.0; right-associative it
+    // would be 4.0
+    let source = "
+        print 8 / 4 / 2; print 10 - 4 - 3; print 20 % 7 % 4;
+        print 17 ~/ 4 ~/ 2; print 2 * 3 % 4; print 2 ** 3 ** 2;
+    ";
+    let output = run("associativity", source, &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "1.0\n3\n2\n2\n2\n512\n");
+}
+
+#[test]
+fn tail_calls_through_conditionals_and_groupings() {
+    let source = "
+        fun loop(n, acc) { return n == 0 ? acc : loop(n - 1, acc + 1); }
+        print loop(100000, 0);
+        fun grouped(n) { if (n == 0) return \"done\"; return (grouped(n - 1)); }
+        print grouped(100000);
+    ";
+    for flags in [&[][..], &["--no-fold"]] {
+        let output = run("tail_calls", source, flags);
+        assert_eq!(output.stderr, "");
+        assert_eq!(output.stdout, "100000\ndone\n");
+        assert_eq!(output.code, Some(0));
+    }
+}
+
+#[test]
+fn huge_depth_limits_still_overflow_cleanly() {
+    let source = "fun f(n) { return 1 + f(n + 1); } f(0);";
+    let output = run("huge_depth", source, &["--max-depth=100000000"]);
+    assert!(
+        output.stderr.starts_with("Stack overflow.\n"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.code, Some(70));
+}
+
+#[test]
+fn a_stack_that_cant_be_reserved_lowers_the_limits() {
+    let path = env::temp_dir().join(format!("rlox-test-{}-ulimit.lox", process::id()));
+    fs::write(&path, "print 1;").unwrap();
+    // ulimit applies to the shell, which then becomes rlox
+    let command = format!(
+        "ulimit -v 400000 && exec '{}' --max-depth=100000000 '{}'",
+        env!("CARGO_BIN_EXE_rlox"),
+        path.display()
+    );
+    let output = Command::new("sh").arg("-c").arg(command).output().unwrap();
+    fs::remove_file(&path).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Lowered the limits to "), "{stderr}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn long_operator_chains_parse() {
+    let numbers = vec!["1"; 4000].join(" + ");
+    let strings = vec!["\"a\""; 300].join(" + ");
+    let source = format!("print {numbers}; print {strings};");
+    for flags in [&[][..], &["--no-fold"]] {
+        let output = run("long_chains", &source, flags);
+        assert_eq!(output.stderr, "");
+        assert_eq!(output.stdout, format!("4000\n{}\n", "a".repeat(300)));
+    }
+}
+
+#[test]
+fn malformed_number_literals_report_once() {
+    for (literal, message) in [
+        ("0x", "Expect digits in hexadecimal literal."),
+        ("0o", "Expect digits in octal literal."),
+        ("0b102", "Invalid digit '2' in binary literal."),
+        ("0xFG", "Invalid digit 'G' in hexadecimal literal."),
+        ("1_", "Expect digit after '_' in number literal."),
+        ("1e", "Expect digits in exponent."),
+        ("1e99999999999999999999d", "Exponent too large."),
+    ] {
+        let output = run("malformed_numbers", &format!("print {literal};"), &[]);
+        assert_eq!(output.stderr, format!("[line 1] Error: {message}\n"));
+        assert_eq!(output.code, Some(65));
+    }
+}
+
+#[test]
+fn escapes_and_raw_strings() {
+    let source = r#"print "tab\there \u{1F600} \\ \"q\"\$"; print r"a\n\";"#;
+    let output = run("escapes", source, &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "tab\there \u{1F600} \\ \"q\"$\na\\n\\\n");
+
+    for (source, message) in [
+        (
+            r#"print "a\qb";"#,
+            r"Unknown escape sequence '\q' at column 9.",
+        ),
+        (
+            r#"print "\u{110000}";"#,
+            "Invalid unicode escape sequence at column 8.",
+        ),
+        (
+            r#"print "\u{}";"#,
+            "Invalid unicode escape sequence at column 8.",
+        ),
+    ] {
+        let output = run("escapes", source, &[]);
+        assert_eq!(output.stderr, format!("[line 1] Error: {message}\n"));
+        assert_eq!(output.code, Some(65));
+    }
+}
+
+#[test]
+fn unterminated_interpolation_is_reported() {
+    let output = run("interpolation", "print \"sum: ${1 + 2", &[]);
+    assert!(
+        output
+            .stderr
+            .starts_with("[line 1] Error: Unterminated string interpolation.\n"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.code, Some(65));
+}
+
+#[test]
+fn for_loops_follow_the_iterator_protocol() {
+    let source = "
+        fun countdown(n) {
+            return fun () {
+                if (n == 0) return done;
+                n = n - 1;
+                return n + 1;
+            };
+        }
+        for (var x in countdown(3)) print x;
+
+        fun span(start, end) {
+            return {\"iter\": fun () {
+                var i = start;
+                return {\"next\": fun () {
+                    if (i >= end) return done;
+                    i = i + 1;
+                    return i - 1;
+                }};
+            }};
+        }
+        var twice = span(5, 7);
+        for (var x in twice) print x;
+        for (var x in twice) print x * 10;
+
+        // a map without protocol functions still iterates its keys
+        for (var key in {\"next\": 1}) print key;
+        for (var x in {\"iter\": fun () { return 1; }}) print x;
+    ";
+    let output = run("iterator_protocol", source, &[]);
+    assert_eq!(output.stdout, "3\n2\n1\n5\n6\n50\n60\nnext\n");
+    assert!(
+        output
+            .stderr
+            .starts_with("'iter' must return a function or a map with a 'next' function.\n"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn break_and_continue_only_work_in_loops() {
+    let source = "
+        var i = 0;
+        while (true) {
+            i = i + 1;
+            if (i == 2) continue;
+            if (i == 4) break;
+            print i;
+        }
+        for (var x in [1, 2, 3]) { if (x == 2) continue; print x; }
+    ";
+    let output = run("loop_jumps", source, &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "1\n3\n1\n3\n");
+
+    let source = "
+        break;
+        while (true) { fun skip() { continue; } break; }
+    ";
+    let output = run("loop_jumps", source, &[]);
+    assert_eq!(output.stdout, "");
+    assert_eq!(
+        output.stderr,
+        "[line 2] Error: at 'break' Can't use 'break' outside of a loop.\n\
+         [line 3] Error: at 'continue' Can't use 'continue' outside of a loop.\n"
+    );
+    assert_eq!(output.code, Some(65));
+}
+
+#[test]
+fn the_prompt_runs_loops_and_recovers_from_errors() {
+    let input = "var i = 0;\n\
+        while (i < 5) { i = i + 1; if (i == 2) continue; if (i == 4) break; print i; }\n\
+        break;\n\
+        print i;\n";
+    let output = run_prompt(input, &[]);
+    assert_eq!(output.stdout, "> > 1\n3\n> > 4\n> ");
+    assert_eq!(
+        output.stderr,
+        "[line 1] Error: at 'break' Can't use 'break' outside of a loop.\n"
+    );
+    assert_eq!(output.code, Some(0));
+}
+
+#[test]
+fn columns_count_characters_from_the_line_start() {
+    let output = run("columns", "var s = \"é\nü\"; print nope;", &[]);
+    assert!(
+        output.stderr.contains(":2:11] in script"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn argument_errors_name_what_is_wrong() {
+    let functions = "
+        fun f(a, b = 2, c = 3) { return a + b + c; }
+        fun one(a) {}
+        fun rest(a, ...more) {}
+        fun pair(a, b) {}
+        fun three(a, b, c) {}
+    ";
+    for (call, message) in [
+        ("f()", "Expected 1 to 3 arguments but got 0."),
+        ("f(1, 2, 3, 4)", "Expected 1 to 3 arguments but got 4."),
+        ("one(1, 2)", "Expected 1 arguments but got 2."),
+        ("rest()", "Expected at least 1 arguments but got 0."),
+        ("f(1, d: 2)", "Unexpected argument 'd'."),
+        ("f(1, a: 2)", "Got multiple values for argument 'a'."),
+        ("f(b: 2)", "Missing argument 'a'."),
+        ("pair(c: 1)", "Unexpected argument 'c'."),
+        ("pair(b: 1)", "Missing argument 'a'."),
+        ("f(c: 1, b: 2)", "Missing argument 'a'."),
+        ("rest(2, a: 1)", "Got multiple values for argument 'a'."),
+        ("f(c: 1)", "Missing argument 'a'."),
+        ("rest(more: 1)", "Unexpected argument 'more'."),
+        ("three(c: 1)", "Missing arguments 'a', 'b'."),
+    ] {
+        let output = run("arguments", &format!("{functions} print {call};"), &[]);
+        assert!(
+            output.stderr.starts_with(&format!("{message}\n")),
+            "{call}: {}",
+            output.stderr
+        );
+        assert_eq!(output.code, Some(70));
+    }
+
+    let output = run("arguments", &format!("{functions} print f(1, c: 10);"), &[]);
+    assert_eq!(output.stdout, "13\n");
+}
+
+#[test]
+fn initializers_stop_at_a_comma() {
+    let source = "var b = 0; var a = 1, b = 2; print a; print b;";
+    let output = run("initializer_comma", source, &[]);
+    assert_eq!(output.stdout, "");
+    assert_eq!(
+        output.stderr,
+        "[line 1] Error: at ',' Expect ';' after variable declaration.\n"
+    );
+    assert_eq!(output.code, Some(65));
+}
+
+#[test]
+fn constants_cant_be_assigned_or_redeclared() {
+    let output = run("const_assign", "const X = 1; X = 2;", &[]);
+    assert_eq!(
+        output.stderr,
+        "[line 1] Error: at 'X' Cannot assign to constant 'X'.\n"
+    );
+    assert_eq!(output.code, Some(65));
+
+    let source = "fun f() { X = 2; } const X = 1; f();";
+    let output = run("const_assign_closure", source, &[]);
+    assert!(
+        output
+            .stderr
+            .starts_with("Cannot assign to constant 'X'.\n"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.code, Some(70));
+
+    let source = "const X = 1; { var X = 2; print X; } var X = 3;";
+    let output = run("const_redeclare", source, &[]);
+    assert_eq!(output.stdout, "");
+    assert_eq!(
+        output.stderr,
+        "[line 1] Error: at 'X' Can't redeclare constant 'X'.\n"
+    );
+    assert_eq!(output.code, Some(65));
+
+    let output = run_prompt("const X = 1;\nvar X = 2;\nprint X;\n", &[]);
+    assert_eq!(output.stdout, "> > > 1\n> ");
+    assert!(
+        output.stderr.starts_with("Can't redeclare constant 'X'.\n"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn self_containing_lists_and_maps_compare() {
+    let source = "
+        var a = []; a.push(a); var b = []; b.push(b);
+        var c = [1]; c.push(c); var d = [2]; d.push(d);
+        var m = {}; m[\"self\"] = m; var n = {}; n[\"self\"] = n;
+        print a == b; print c == d; print m == n;
+    ";
+    let output = run("cycles", source, &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "true\nfalse\ntrue\n");
+}
+
+#[test]
+fn empty_sets_read_back() {
+    let source = "
+        var s = {,}; print s; print s == {1} - {1}; print s.len();
+        s.add(1); print s; print ({,}) == ({});
+    ";
+    let output = run("empty_sets", source, &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "{,}\ntrue\n0\n{1}\nfalse\n");
+}
+
+#[test]
+fn numbers_convert_explicitly() {
+    let source = "
+        print int(2.0); print int(2.50d * 2); print int(1e20);
+        print decimal(0.1) == 0.1d; print decimal(3) + 0.25d;
+        print float(0.1d) + 0.2; print float(7);
+    ";
+    let output = run("conversions", source, &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(
+        output.stdout,
+        "2\n5\n100000000000000000000\ntrue\n3.25\n0.30000000000000004\n7.0\n"
+    );
+
+    for (source, message) in [
+        (
+            "int(2.5)",
+            "Can't convert a number with a fractional part to an integer.",
+        ),
+        (
+            "int(1.5d)",
+            "Can't convert a number with a fractional part to an integer.",
+        ),
+        ("int(1 / 0)", "Number is out of range for the conversion."),
+        (
+            "decimal(-1 / 0)",
+            "Number is out of range for the conversion.",
+        ),
+        (
+            "float(10 ** 400)",
+            "Number is out of range for the conversion.",
+        ),
+        ("float(\"1\")", "Argument must be a number."),
+    ] {
+        let output = run("conversions", &format!("print {source};"), &[]);
+        assert!(
+            output.stderr.starts_with(message),
+            "{source}: {}",
+            output.stderr
+        );
+        assert_eq!(output.code, Some(70));
+    }
+}
+
+#[test]
+fn deeply_nested_collections_print_and_compare() {
+    let source = "
+        var a = []; var b = []; var m = {}; var n = {}; var t = (); var i = 0;
+        while (i < 100000) {
+            a = [a]; b = [b]; m = {\"k\": m}; n = {\"k\": n}; t = (t,); i = i + 1;
+        }
+        print a == b; print m == n; print t == (t,); print {t}.has(t);
+        var text = \"${a} ${m} ${t}\"; print text == \"\";
+    ";
+    let output = run("deep_collections", source, &[]);
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.stdout, "true\ntrue\nfalse\ntrue\nfalse\n");
+}
+
+#[test]
+fn huge_powers_are_refused() {
+    let source = "print 1 ** 4000000000; print 3 ** 100000000;";
+    let output = run("huge_powers", source, &["--no-fold"]);
+    assert_eq!(output.stdout, "1\n");
+    assert!(
+        output.stderr.starts_with("Exponent is too large.\n"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.code, Some(70));
+}